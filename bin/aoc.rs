@@ -0,0 +1,198 @@
+use adventofcode::days::day3::{self, Report};
+use adventofcode::days::{self, Day, Solution, Unsolved};
+use adventofcode::intcode::topology::Topology;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use failure::{err_msg, Fallible, ResultExt};
+use std::path::Path;
+
+fn default_input(day: &Day) -> String {
+    format!("inputs/day{}.txt", day.number)
+}
+
+fn solve(day: &Day, parts: &[u32], path: &str) -> Fallible<()> {
+    let text = std::fs::read_to_string(path).with_context(|_| path.to_string())?;
+    let answers = (day.solve)(&text, parts).with_context(|_| format!("day {}", day.number))?;
+    for answer in answers {
+        println!(
+            "day {} part {}: {} ({:.1?})",
+            day.number, answer.part, answer.value, answer.elapsed
+        );
+    }
+    Ok(())
+}
+
+fn run(matches: &ArgMatches) -> Fallible<()> {
+    let parts = match matches.value_of("part") {
+        Some(part) => vec![part.parse()?],
+        None => vec![1, 2],
+    };
+    if matches.is_present("all") {
+        for day in days::ALL {
+            let path = default_input(day);
+            if Path::new(&path).exists() {
+                solve(day, &parts, &path)?;
+            } else {
+                eprintln!("day {}: skipped, there's no {}", day.number, path);
+            }
+        }
+        return Ok(());
+    }
+    // clap makes sure there's a day when there's no --all
+    let day = day(matches)?;
+    solve(day, &parts, &input(day, matches))
+}
+
+fn day(matches: &ArgMatches) -> Fallible<&'static Day> {
+    let number: u32 = matches
+        .value_of("day")
+        .unwrap()
+        .parse::<u32>()
+        .context("--day should be a number")?;
+    Ok(days::find(number).ok_or(Unsolved {
+        day: number,
+        part: None,
+    })?)
+}
+
+fn input(day: &Day, matches: &ArgMatches) -> String {
+    match matches.value_of("input") {
+        Some(path) => path.to_string(),
+        None => default_input(day),
+    }
+}
+
+fn plot(matches: &ArgMatches) -> Fallible<()> {
+    let day = day(matches)?;
+    let plot = day
+        .plot
+        .ok_or_else(|| err_msg(format!("day {} has nothing to plot", day.number)))?;
+    let path = input(day, matches);
+    let text = std::fs::read_to_string(&path).with_context(|_| path.clone())?;
+    let svg = plot(&text).with_context(|_| format!("day {}", day.number))?;
+    // clap makes sure there's an output
+    let output = matches.value_of("output").unwrap();
+    std::fs::write(output, svg).with_context(|_| output.to_string())?;
+    Ok(())
+}
+
+/// Where any number of day 3 wires cross, pair by pair.
+fn wires(matches: &ArgMatches) -> Fallible<()> {
+    let path = matches.value_of("input").unwrap_or("inputs/day3.txt");
+    let k: usize = matches
+        .value_of("at-least")
+        .unwrap()
+        .parse::<usize>()
+        .context("--at-least should be a number")?;
+    let text = std::fs::read_to_string(path).with_context(|_| path.to_string())?;
+    let wires = day3::Puzzle::parse(&text).context("day 3")?;
+    print!("{}", Report::new(&wires, k));
+    Ok(())
+}
+
+fn topology(matches: &ArgMatches) -> Fallible<()> {
+    // clap makes sure there's a file
+    let topology = Topology::load(matches.value_of("file").unwrap())?;
+    print!("{}", topology.run()?);
+    Ok(())
+}
+
+fn main() {
+    let matches = App::new("aoc")
+        .about("Advent of Code 2019 solutions")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("run")
+                .about("Prints the answers for a day, or every day")
+                .arg(
+                    Arg::with_name("day")
+                        .long("day")
+                        .takes_value(true)
+                        .required_unless("all"),
+                )
+                .arg(
+                    Arg::with_name("part")
+                        .long("part")
+                        .takes_value(true)
+                        .possible_values(&["1", "2"])
+                        .help("Only this part instead of both"),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .long("input")
+                        .takes_value(true)
+                        .conflicts_with("all")
+                        .help("Puzzle input [default: inputs/dayN.txt]"),
+                )
+                .arg(
+                    Arg::with_name("all")
+                        .long("all")
+                        .conflicts_with("day")
+                        .help("Runs every solved day that has its default input"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("plot")
+                .about("Draws a day's input as an SVG")
+                .arg(
+                    Arg::with_name("day")
+                        .long("day")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .long("input")
+                        .takes_value(true)
+                        .help("Puzzle input [default: inputs/dayN.txt]"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Where to write the SVG"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("wires")
+                .about("Reports where day 3 wires cross, for any number of them")
+                .arg(
+                    Arg::with_name("input")
+                        .long("input")
+                        .takes_value(true)
+                        .help("One wire per line [default: inputs/day3.txt]"),
+                )
+                .arg(
+                    Arg::with_name("at-least")
+                        .long("at-least")
+                        .takes_value(true)
+                        .default_value("3")
+                        .help("Also lists the points this many wires pass through"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("topology")
+                .about("Runs an Intcode network described in a topology file")
+                .arg(
+                    Arg::with_name("file")
+                        .required(true)
+                        .help("Like inputs/day7-part1.topology"),
+                ),
+        )
+        .get_matches();
+
+    let result = match matches.subcommand() {
+        ("run", Some(matches)) => run(matches),
+        ("plot", Some(matches)) => plot(matches),
+        ("wires", Some(matches)) => wires(matches),
+        ("topology", Some(matches)) => topology(matches),
+        _ => unreachable!(),
+    };
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        for cause in e.iter_causes() {
+            eprintln!("  caused by: {}", cause);
+        }
+        std::process::exit(1);
+    }
+}
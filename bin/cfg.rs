@@ -0,0 +1,19 @@
+use adventofcode::intcode::cfg::ControlFlowGraph;
+use adventofcode::intcode::load_program;
+use failure::Fallible;
+
+fn main() -> Fallible<()> {
+    let fname: String = std::env::args().skip(1).take(1).collect();
+    let data = load_program(fname)?;
+
+    let cfg = ControlFlowGraph::build(&data);
+    print!("{}", cfg.to_dot());
+
+    for addr in cfg.indirect_jumps() {
+        eprintln!("indirect jump at {}", addr);
+    }
+    for range in cfg.unreachable() {
+        eprintln!("unreachable: {}..{}", range.start, range.end);
+    }
+    Ok(())
+}
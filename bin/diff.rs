@@ -0,0 +1,47 @@
+use adventofcode::intcode::devices::Rng;
+use adventofcode::intcode::differential::{compare, Current};
+use adventofcode::intcode::fuzz::{Case, Config};
+use adventofcode::intcode::{legacy, load_program};
+use failure::Fallible;
+
+const MAX_STEPS: usize = 1_000_000;
+
+/// Runs each program given on the command line through the day 2 machine and the current one.
+/// With `--random N`, also tries N generated day 2 programs.
+fn main() -> Fallible<()> {
+    let mut args = std::env::args().skip(1);
+    let mut programs = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "--random" {
+            let count: u64 = args.next().unwrap_or_default().parse()?;
+            let config = Config {
+                instructions: 16,
+                data: 8,
+                day2: true,
+                ..Config::default()
+            };
+            programs.extend((1..=count).map(|seed| {
+                let case = Case::generate(&mut Rng::new(seed), &config);
+                (format!("seed {}", seed), case.image())
+            }));
+        } else {
+            programs.push((arg.clone(), load_program(&arg)?));
+        }
+    }
+
+    // the day 2 machine panics on overflow, and compare() reports that itself
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let mut diverged = 0;
+    for (name, program) in programs.iter() {
+        let mut old = legacy::IntcodeMachine::new(program);
+        let mut new = Current::new(program, Vec::new());
+        if let Some(divergence) = compare(&mut old, &mut new, MAX_STEPS) {
+            println!("{}: {}", name, divergence);
+            diverged += 1;
+        }
+    }
+    std::panic::set_hook(hook);
+    println!("{} of {} programs diverged", diverged, programs.len());
+    Ok(())
+}
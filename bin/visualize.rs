@@ -0,0 +1,102 @@
+use adventofcode::intcode::visual::{render, Recorder, View};
+use adventofcode::intcode::{load_program, Instruction, IntcodeMachine, Opcode};
+use crossbeam::channel::{unbounded, Receiver};
+use failure::Fallible;
+use std::io::{self, BufRead, Write};
+use std::thread;
+use std::time::Duration;
+
+const USAGE: &str = "usage: intcode-tui PROGRAM [--input 1,2,..] [--delay MS] [--columns N] \
+                     [--rows N] [--hex]";
+
+const HELP: &str = "enter: + faster, - slower, p pause, s step, h hex, q quit";
+
+/// Reads control lines from stdin, since the program's input comes from `--input`.
+fn controls() -> Receiver<String> {
+    let (tx, rx) = unbounded();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines().map_while(Result::ok) {
+            if tx.send(line.trim().to_string()).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+/// Steps a program one instruction per frame, drawing memory, the next instruction and its I/O.
+fn main() -> Fallible<()> {
+    let mut args = std::env::args().skip(1);
+    let mut file = None;
+    let mut inputs = Vec::new();
+    let mut delay = 100;
+    let mut view = View::default();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| failure::err_msg(USAGE));
+        match arg.as_str() {
+            "--input" => {
+                for x in value()?.split(',') {
+                    inputs.push(x.trim().parse::<i32>()?);
+                }
+            }
+            "--delay" => delay = value()?.parse()?,
+            "--columns" => view.columns = value()?.parse()?,
+            "--rows" => view.rows = value()?.parse()?,
+            "--hex" => view.hex = true,
+            _ => file = Some(arg),
+        }
+    }
+    let data = load_program(file.ok_or_else(|| failure::err_msg(USAGE))?)?;
+
+    // Vec inputs pop from the back
+    let queue: Vec<i32> = inputs.iter().rev().cloned().collect();
+    let mut machine = IntcodeMachine::new(&data, Box::new(queue), Box::new(Vec::new()));
+    let recorder = Recorder::new();
+    let activity = recorder.activity();
+    machine.add_hook(Box::new(recorder));
+
+    let controls = controls();
+    let mut paused = false;
+    let mut status = String::from("running");
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b[2J")?;
+    loop {
+        let mut step = !paused;
+        for line in controls.try_iter() {
+            match line.as_str() {
+                "+" => delay /= 2,
+                "-" => delay = (delay * 2).max(1),
+                "p" => paused = !paused,
+                "s" => step = true,
+                "h" => view.hex = !view.hex,
+                "q" => return Ok(()),
+                _ => {}
+            }
+        }
+
+        let running = status == "running";
+        if step && running {
+            let result = machine.unpack_op().and_then(|op| match op {
+                Opcode::Halt => Ok(false),
+                op => op.execute(&mut machine).map(|_| true),
+            });
+            match result {
+                Ok(true) => {}
+                Ok(false) => status = "halted".to_string(),
+                Err(e) => status = format!("error: {}", e),
+            }
+        }
+
+        let activity = activity.borrow();
+        let pending = &inputs[activity.inputs.len().min(inputs.len())..];
+        let state = if paused && running { "paused" } else { &status };
+        write!(stdout, "{}", render(&machine, &activity, pending, &view))?;
+        writeln!(
+            stdout,
+            "{} | {}ms/step | {}\x1b[K\x1b[J",
+            state, delay, HELP
+        )?;
+        stdout.flush()?;
+        thread::sleep(Duration::from_millis(delay.max(1)));
+    }
+}
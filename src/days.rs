@@ -0,0 +1,119 @@
+//! Every solved day, for the `aoc` command line.
+//!
+//! A day implements [`Solution`]: parse the input once, then answer each part from it. [`ALL`]
+//! lists them by number, with the types erased so they all fit in one table.
+use failure::{Fallible, ResultExt};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::time::{Duration, Instant};
+
+pub mod day1;
+pub mod day2;
+pub mod day3;
+pub mod day4;
+pub mod day5;
+pub mod day7;
+
+pub trait Solution {
+    type Input;
+    type Answer: Display;
+
+    fn parse(input: &str) -> Fallible<Self::Input>;
+    fn part1(input: &Self::Input) -> Fallible<Self::Answer>;
+    fn part2(input: &Self::Input) -> Fallible<Self::Answer>;
+}
+
+/// One part's answer, and how long it took on top of parsing.
+pub struct Answer {
+    pub part: u32,
+    pub value: String,
+    pub elapsed: Duration,
+}
+
+pub struct Day {
+    pub number: u32,
+    /// Answers each of `parts` for the puzzle input, parsing it once.
+    pub solve: fn(input: &str, parts: &[u32]) -> Fallible<Vec<Answer>>,
+    /// Draws the puzzle input as an SVG, for the days that can.
+    pub plot: Option<fn(input: &str) -> Fallible<String>>,
+}
+
+fn solve<S: Solution>(number: u32, input: &str, parts: &[u32]) -> Fallible<Vec<Answer>> {
+    let input = S::parse(input)?;
+    let mut answers = Vec::new();
+    for part in parts {
+        let start = Instant::now();
+        let value = match part {
+            1 => S::part1(&input),
+            2 => S::part2(&input),
+            _ => {
+                return Err(Unsolved {
+                    day: number,
+                    part: Some(*part),
+                }
+                .into())
+            }
+        };
+        let value = value.with_context(|_| format!("part {}", part))?;
+        answers.push(Answer {
+            part: *part,
+            value: value.to_string(),
+            elapsed: start.elapsed(),
+        });
+    }
+    Ok(answers)
+}
+
+pub const ALL: &[Day] = &[
+    Day {
+        number: 1,
+        solve: |input, parts| solve::<day1::Puzzle>(1, input, parts),
+        plot: None,
+    },
+    Day {
+        number: 2,
+        solve: |input, parts| solve::<day2::Puzzle>(2, input, parts),
+        plot: None,
+    },
+    Day {
+        number: 3,
+        solve: |input, parts| solve::<day3::Puzzle>(3, input, parts),
+        plot: Some(day3::plot),
+    },
+    Day {
+        number: 4,
+        solve: |input, parts| solve::<day4::Puzzle>(4, input, parts),
+        plot: None,
+    },
+    Day {
+        number: 5,
+        solve: |input, parts| solve::<day5::Puzzle>(5, input, parts),
+        plot: None,
+    },
+    Day {
+        number: 7,
+        solve: |input, parts| solve::<day7::Puzzle>(7, input, parts),
+        plot: None,
+    },
+];
+
+pub fn find(number: u32) -> Option<&'static Day> {
+    ALL.iter().find(|day| day.number == number)
+}
+
+/// Asked for a day or part nobody has solved.
+#[derive(Debug)]
+pub struct Unsolved {
+    pub day: u32,
+    pub part: Option<u32>,
+}
+
+impl Display for Unsolved {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        match self.part {
+            Some(part) => write!(w, "day {} has no part {}", self.day, part),
+            None => write!(w, "day {} isn't solved", self.day),
+        }
+    }
+}
+
+impl std::error::Error for Unsolved {}
@@ -0,0 +1,62 @@
+//! The Tyranny of the Rocket Equation.
+use super::Solution;
+use failure::Fallible;
+
+fn fuel_per_mass(x: i64) -> i64 {
+    (x / 3) - 2
+}
+
+fn total_fuel_per_mass(x: i64) -> i64 {
+    let mut total = 0;
+    let mut mass = fuel_per_mass(x);
+    while mass > 0 {
+        total += mass;
+        mass = fuel_per_mass(mass);
+    }
+    total
+}
+
+pub struct Puzzle;
+
+impl Solution for Puzzle {
+    type Input = Vec<i64>;
+    type Answer = i64;
+
+    fn parse(input: &str) -> Fallible<Vec<i64>> {
+        input
+            .lines()
+            .map(|line| line.trim().parse().map_err(Into::into))
+            .collect()
+    }
+
+    fn part1(masses: &Vec<i64>) -> Fallible<i64> {
+        Ok(masses.iter().cloned().map(fuel_per_mass).sum())
+    }
+
+    fn part2(masses: &Vec<i64>) -> Fallible<i64> {
+        Ok(masses.iter().cloned().map(total_fuel_per_mass).sum())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_samples() {
+        assert_eq!(fuel_per_mass(14), 2);
+    }
+
+    #[test]
+    fn test_totals_samples() {
+        assert_eq!(total_fuel_per_mass(14), 2);
+        assert_eq!(total_fuel_per_mass(1969), 966);
+    }
+
+    #[test]
+    fn test_puzzle() {
+        let masses = Puzzle::parse("12\n14\n1969\n100756\n").unwrap();
+        assert_eq!(Puzzle::part1(&masses).unwrap(), 2 + 2 + 654 + 33583);
+        assert_eq!(Puzzle::part2(&masses).unwrap(), 2 + 2 + 966 + 50346);
+        assert!(Puzzle::parse("12\nfourteen\n").is_err());
+    }
+}
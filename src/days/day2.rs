@@ -0,0 +1,44 @@
+//! 1202 Program Alarm.
+use super::Solution;
+use crate::intcode::symbolic::{solve, Observe, Unknown};
+use crate::intcode::{parse_program, IntcodeMachine, Parameter};
+use failure::{Fallible, ResultExt};
+
+pub struct Puzzle;
+
+impl Solution for Puzzle {
+    type Input = Vec<i32>;
+    type Answer = i32;
+
+    fn parse(input: &str) -> Fallible<Vec<i32>> {
+        Ok(parse_program(input))
+    }
+
+    fn part1(data: &Vec<i32>) -> Fallible<i32> {
+        let mut machine = IntcodeMachine::new(data, Box::new(Vec::new()), Box::new(Vec::new()));
+        machine.set_cell(1, 12)?;
+        machine.set_cell(2, 2)?;
+        machine.run()?;
+        machine.value_at(&Parameter::Indirect(0))
+    }
+
+    fn part2(data: &Vec<i32>) -> Fallible<i32> {
+        let unknowns = &[(Unknown::Cell(1), 0..=99), (Unknown::Cell(2), 0..=99)];
+        let solution = solve(data, unknowns, Observe::Cell(0), 19690720)
+            .context("looking for the noun and verb that give 19690720")?;
+        Ok(100 * solution.values[0] + solution.values[1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part1() {
+        // noun + verb, written over the first cell
+        let data = Puzzle::parse("1,0,0,0,99,0,0,0,0,0,0,0,7").unwrap();
+        assert_eq!(Puzzle::part1(&data).unwrap(), 9);
+        assert!(Puzzle::part1(&vec![99]).is_err());
+    }
+}
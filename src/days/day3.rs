@@ -0,0 +1,436 @@
+//! Crossed Wires.
+//!
+//! The puzzle has two wires, but nothing here needs that: any number of them parse the same way,
+//! the answers look at crossings between any two, and [`Report`] breaks them down by pair.
+use self::sweep::Crossing;
+use super::Solution;
+use failure::{err_msg, Fallible, ResultExt};
+use geo::{Coordinate, Line};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+pub mod plot;
+pub mod sweep;
+
+// R123,U22,L22,D10,R10
+
+#[derive(Clone, Copy)]
+enum Direction {
+    X(i32),
+    Y(i32),
+}
+
+/// A move in a wire that isn't a direction letter followed by a distance. `position` counts
+/// characters from the start of the move, or of the whole wire when it came from
+/// parsing one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseDirectionError {
+    /// Nothing between two commas.
+    Empty {
+        position: usize,
+    },
+    UnknownDirection {
+        position: usize,
+        found: char,
+    },
+    /// Missing, not a plain number, or too big.
+    BadDistance {
+        position: usize,
+        text: String,
+    },
+    /// A move that takes the wire further out than an `i32` reaches.
+    OutOfRange {
+        position: usize,
+    },
+}
+
+impl ParseDirectionError {
+    fn shifted(self, by: usize) -> ParseDirectionError {
+        use ParseDirectionError::*;
+        match self {
+            Empty { position } => Empty {
+                position: position + by,
+            },
+            UnknownDirection { position, found } => UnknownDirection {
+                position: position + by,
+                found,
+            },
+            BadDistance { position, text } => BadDistance {
+                position: position + by,
+                text,
+            },
+            OutOfRange { position } => OutOfRange {
+                position: position + by,
+            },
+        }
+    }
+}
+
+impl Display for ParseDirectionError {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        match self {
+            ParseDirectionError::Empty { position } => {
+                write!(w, "empty move at position {}", position)
+            }
+            ParseDirectionError::UnknownDirection { position, found } => write!(
+                w,
+                "expected one of R, L, U or D at position {}, found {:?}",
+                position, found
+            ),
+            ParseDirectionError::BadDistance { position, text } => {
+                write!(w, "bad distance {:?} at position {}", text, position)
+            }
+            ParseDirectionError::OutOfRange { position } => {
+                write!(w, "the move at position {} goes out of range", position)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseDirectionError {}
+
+impl FromStr for Direction {
+    type Err = ParseDirectionError;
+    fn from_str(s: &str) -> Result<Direction, Self::Err> {
+        let mut chars = s.chars();
+        let letter = chars
+            .next()
+            .ok_or(ParseDirectionError::Empty { position: 0 })?;
+        let number = chars.as_str();
+        let bad_distance = || ParseDirectionError::BadDistance {
+            position: 1,
+            text: number.to_string(),
+        };
+        // parse() alone would take a sign
+        if !number.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(bad_distance());
+        }
+        let number_part: i32 = number.parse().map_err(|_| bad_distance())?;
+        match letter {
+            'R' => Ok(Direction::X(number_part)),
+            'L' => Ok(Direction::X(-number_part)),
+            'U' => Ok(Direction::Y(number_part)),
+            'D' => Ok(Direction::Y(-number_part)),
+            found => Err(ParseDirectionError::UnknownDirection { position: 0, found }),
+        }
+    }
+}
+
+impl Direction {
+    /// The segment this move draws from `start`, unless its end doesn't fit an `i32`.
+    fn from(self, start: Coordinate<i32>) -> Option<Line<i32>> {
+        let end = match self {
+            Direction::X(x) => Coordinate {
+                x: start.x.checked_add(x)?,
+                y: start.y,
+            },
+            Direction::Y(y) => Coordinate {
+                x: start.x,
+                y: start.y.checked_add(y)?,
+            },
+        };
+        Some(Line { start, end })
+    }
+}
+
+/// The segments of one wire, starting at the origin.
+fn wire(moves: &str) -> Result<Vec<Line<i32>>, ParseDirectionError> {
+    let mut origin = Coordinate { x: 0, y: 0 };
+    let mut position = 0;
+    let mut lines = Vec::new();
+    for step in moves.split(',') {
+        let trimmed = step.trim_start();
+        let start = position + step.chars().count() - trimmed.chars().count();
+        let dir: Direction = trimmed
+            .trim_end()
+            .parse()
+            .map_err(|e: ParseDirectionError| e.shifted(start))?;
+        let line = dir
+            .from(origin)
+            .ok_or(ParseDirectionError::OutOfRange { position: start })?;
+        origin = line.end;
+        lines.push(line);
+        position += step.chars().count() + 1;
+    }
+    Ok(lines)
+}
+
+fn manhattan(a: Coordinate<i32>, b: Coordinate<i32>) -> i64 {
+    (i64::from(a.x) - i64::from(b.x)).abs() + (i64::from(a.y) - i64::from(b.y)).abs()
+}
+
+/// The crossing nearest the origin.
+fn closest(crossings: &[Crossing]) -> Option<&Crossing> {
+    let origin = Coordinate { x: 0, y: 0 };
+    crossings.iter().min_by_key(|c| manhattan(origin, c.point))
+}
+
+/// The crossing both wires reach in the fewest combined steps.
+fn quickest(crossings: &[Crossing]) -> Option<&Crossing> {
+    crossings.iter().min_by_key(|c| c.steps.0 + c.steps.1)
+}
+
+/// Where any two of the wires cross.
+fn crossings(wires: &[Vec<Line<i32>>]) -> Vec<Crossing> {
+    let lines: Vec<&[Line<i32>]> = wires.iter().map(|wire| &wire[..]).collect();
+    sweep::all(&lines)
+}
+
+/// Points at least `k` of the wires pass through, with the wires that do, ordered by x and then
+/// y. Only points two wires cross are considered, so a `k` below 2 is the same as 2. The
+/// crossings can come in any order.
+pub fn shared(crossings: &[Crossing], k: usize) -> Vec<(Coordinate<i32>, Vec<usize>)> {
+    let mut shared: BTreeMap<(i32, i32), BTreeSet<usize>> = BTreeMap::new();
+    for crossing in crossings {
+        let (x, y) = (crossing.point.x, crossing.point.y);
+        let wires = shared.entry((x, y)).or_default();
+        wires.insert(crossing.wires.0);
+        wires.insert(crossing.wires.1);
+    }
+    shared
+        .into_iter()
+        .filter(|(_, wires)| wires.len() >= k)
+        .map(|((x, y), wires)| (Coordinate { x, y }, wires.into_iter().collect()))
+        .collect()
+}
+
+/// What a set of wires has in common.
+pub struct Report {
+    pub wires: usize,
+    /// Ordered by pair, then by the point.
+    pub crossings: Vec<Crossing>,
+    /// The points at least `k` wires pass through.
+    pub k: usize,
+    pub shared: Vec<(Coordinate<i32>, Vec<usize>)>,
+}
+
+impl Report {
+    pub fn new(wires: &[Vec<Line<i32>>], k: usize) -> Report {
+        let mut crossings = crossings(wires);
+        let shared = shared(&crossings, k);
+        crossings.sort_by_key(|c| c.wires);
+        Report {
+            wires: wires.len(),
+            crossings,
+            k,
+            shared,
+        }
+    }
+
+    /// The crossings of wires `a` and `b`, with `a` below `b`.
+    pub fn pair(&self, a: usize, b: usize) -> &[Crossing] {
+        let start = self.crossings.partition_point(|c| c.wires < (a, b));
+        let end = self.crossings.partition_point(|c| c.wires <= (a, b));
+        &self.crossings[start..end]
+    }
+
+    /// The fewest combined steps for each pair of wires to meet, if they ever do.
+    pub fn delays(&self) -> Vec<((usize, usize), Option<i64>)> {
+        let mut delays = Vec::new();
+        for a in 0..self.wires {
+            for b in a + 1..self.wires {
+                let delay = quickest(self.pair(a, b)).map(|c| c.steps.0 + c.steps.1);
+                delays.push(((a, b), delay));
+            }
+        }
+        delays
+    }
+}
+
+/// Wires are numbered from 1, in the order they came.
+impl Display for Report {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        let point = |p: Coordinate<i32>| format!("({}, {})", p.x, p.y);
+        for ((a, b), delay) in self.delays() {
+            write!(w, "wires {} and {}: ", a + 1, b + 1)?;
+            match delay {
+                Some(delay) => writeln!(w, "fewest combined steps {}", delay)?,
+                None => writeln!(w, "never cross")?,
+            }
+            for crossing in self.pair(a, b) {
+                let (first, second) = crossing.steps;
+                writeln!(
+                    w,
+                    "  {} after {} + {} steps",
+                    point(crossing.point),
+                    first,
+                    second
+                )?;
+            }
+        }
+        writeln!(w, "crossed by at least {} wires:", self.k)?;
+        if self.shared.is_empty() {
+            writeln!(w, "  nowhere")?;
+        }
+        for (at, wires) in self.shared.iter() {
+            let wires: Vec<String> = wires.iter().map(|wire| (wire + 1).to_string()).collect();
+            writeln!(w, "  {} by wires {}", point(*at), wires.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// The puzzle input drawn by [`plot::svg`].
+pub fn plot(input: &str) -> Fallible<String> {
+    let wires = Puzzle::parse(input)?;
+    Ok(plot::svg(&wires, &crossings(&wires)))
+}
+
+pub struct Puzzle;
+
+impl Solution for Puzzle {
+    type Input = Vec<Vec<Line<i32>>>;
+    type Answer = i64;
+
+    fn parse(input: &str) -> Fallible<Vec<Vec<Line<i32>>>> {
+        let mut wires = Vec::new();
+        for (n, line) in input.lines().enumerate() {
+            if !line.trim().is_empty() {
+                wires.push(wire(line).with_context(|_| format!("wire on line {}", n + 1))?);
+            }
+        }
+        if wires.len() < 2 {
+            return Err(err_msg(format!(
+                "expected at least two wires, got {}",
+                wires.len()
+            )));
+        }
+        Ok(wires)
+    }
+
+    /// Distance from the origin to the closest crossing of any two wires.
+    fn part1(wires: &Vec<Vec<Line<i32>>>) -> Fallible<i64> {
+        let origin = Coordinate { x: 0, y: 0 };
+        closest(&crossings(wires))
+            .map(|c| manhattan(origin, c.point))
+            .ok_or_else(|| err_msg("the wires never cross"))
+    }
+
+    /// The fewest combined steps along two wires to where they cross.
+    fn part2(wires: &Vec<Vec<Line<i32>>>) -> Fallible<i64> {
+        quickest(&crossings(wires))
+            .map(|c| c.steps.0 + c.steps.1)
+            .ok_or_else(|| err_msg("the wires never cross"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLES: &[(&str, i64, i64)] = &[
+        ("R8,U5,L5,D3\nU7,R6,D4,L4", 6, 30),
+        (
+            "R75,D30,R83,U83,L12,D49,R71,U7,L72\nU62,R66,U55,R34,D71,R55,D58,R83",
+            159,
+            610,
+        ),
+        (
+            "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51\nU98,R91,D20,R16,D67,R40,U7,R15,U6,R7",
+            135,
+            410,
+        ),
+    ];
+
+    #[test]
+    fn test_examples() {
+        for (input, distance, steps) in EXAMPLES {
+            let wires = Puzzle::parse(input).unwrap();
+            assert_eq!(Puzzle::part1(&wires).unwrap(), *distance, "{}", input);
+            assert_eq!(Puzzle::part2(&wires).unwrap(), *steps, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_vertical_moves_keep_x() {
+        let segments = wire("R8,U5").unwrap();
+        assert_eq!(segments[1].start, Coordinate { x: 8, y: 0 });
+        assert_eq!(segments[1].end, Coordinate { x: 8, y: 5 });
+    }
+
+    #[test]
+    fn test_overlapping_wires() {
+        // both wires run along y = 2 between x = 1 and x = 3
+        let wires = vec![wire("U2,R5").unwrap(), wire("R1,U2,R2,D5").unwrap()];
+        let crossings = crossings(&wires);
+        let points: Vec<(i32, i32)> = crossings.iter().map(|c| (c.point.x, c.point.y)).collect();
+        assert_eq!(points, vec![(1, 2), (2, 2), (3, 2)]);
+        assert_eq!(crossings[0].steps, (3, 3));
+        assert!(Puzzle::parse("R1").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        use ParseDirectionError::*;
+        assert_eq!(wire("R8,,U5"), Err(Empty { position: 3 }));
+        assert_eq!(
+            wire("R8, X5"),
+            Err(UnknownDirection {
+                position: 4,
+                found: 'X'
+            })
+        );
+        for (moves, position, text) in [
+            ("U", 1, ""),
+            ("R8,L-2", 4, "-2"),
+            ("D99999999999", 1, "99999999999"),
+        ] {
+            let text = text.to_string();
+            assert_eq!(wire(moves), Err(BadDistance { position, text }));
+        }
+        assert_eq!(
+            wire("R2000000000,R2000000000"),
+            Err(OutOfRange { position: 12 })
+        );
+        // i32::MIN is as far as it goes
+        assert!(wire("L2147483647,D1,L1").is_ok());
+        assert_eq!(wire("L2147483647, D1,L2"), Err(OutOfRange { position: 16 }));
+        let error = Puzzle::parse("R8,U5\nU7,R6,Q4").unwrap_err();
+        assert_eq!(error.to_string(), "wire on line 2");
+        let cause = error.iter_causes().next().unwrap().to_string();
+        assert_eq!(
+            cause,
+            "expected one of R, L, U or D at position 6, found 'Q'"
+        );
+    }
+
+    #[test]
+    fn test_far_out_wires() {
+        // every coordinate fits an i32, but the distances and step counts don't
+        let wires = Puzzle::parse("R2147483647,U1,L2147483647\nU1,L2147483647,D2\n").unwrap();
+        assert_eq!(Puzzle::part1(&wires).unwrap(), 1);
+        assert_eq!(Puzzle::part2(&wires).unwrap(), 2 * 2147483647 + 2);
+    }
+
+    #[test]
+    fn test_many_wires() {
+        // the first example with a third wire through both of its crossings, which runs along
+        // the second wire for a while on the way
+        let wires = Puzzle::parse("R8,U5,L5,D3\nU7,R6,D4,L4\nU3,R3,R3,U2\n").unwrap();
+        assert_eq!(Puzzle::part1(&wires).unwrap(), 1);
+        assert_eq!(Puzzle::part2(&wires).unwrap(), 2);
+
+        let report = Report::new(&wires, 3);
+        assert_eq!(report.pair(0, 1).len(), 2);
+        assert_eq!(report.pair(1, 2).len(), 10);
+        assert_eq!(
+            report.delays(),
+            vec![((0, 1), Some(30)), ((0, 2), Some(26)), ((1, 2), Some(2))]
+        );
+        let at = |x, y| Coordinate { x, y };
+        assert_eq!(
+            report.shared,
+            vec![(at(3, 3), vec![0, 1, 2]), (at(6, 5), vec![0, 1, 2])]
+        );
+        assert!(report.to_string().starts_with(
+            "wires 1 and 2: fewest combined steps 30\n  (3, 3) after 20 + 20 steps\n"
+        ));
+        // ordered by pair, not by point, and it doesn't matter
+        assert_eq!(shared(&report.crossings, 3), report.shared);
+        let mut reversed = report.crossings.clone();
+        reversed.reverse();
+        assert_eq!(shared(&reversed, 2).len(), 10);
+        assert_eq!(shared(&reversed, 4), vec![]);
+    }
+}
@@ -0,0 +1,146 @@
+//! Drawing the wires as an SVG, to see what an input looks like.
+//!
+//! Each wire is a polyline in its own colour, starting from the origin in black. Every crossing
+//! gets a small dot; the closest one to the origin is circled in blue and the one with the
+//! fewest combined steps in red. SVG puts y downwards, so points are drawn with y flipped to
+//! keep "U" pointing up.
+use super::sweep::Crossing;
+use super::{closest, quickest};
+use geo::algorithm::bounding_rect::BoundingRect;
+use geo::{Coordinate, Line, LineString};
+use std::fmt::Write;
+
+const COLOURS: &[&str] = &["#2a9d8f", "#e9c46a", "#8e6c8a", "#f4a261", "#577590"];
+const WIDTH: i64 = 800;
+
+fn point(c: Coordinate<i32>) -> String {
+    format!("{},{}", c.x, -i64::from(c.y))
+}
+
+fn circle(svg: &mut String, at: Coordinate<i32>, r: i64, style: &str) {
+    writeln!(
+        svg,
+        r#"  <circle cx="{}" cy="{}" r="{}" {}/>"#,
+        at.x,
+        -i64::from(at.y),
+        r,
+        style
+    )
+    .unwrap();
+}
+
+/// The wires and where they cross, as a standalone SVG document.
+pub fn svg(wires: &[Vec<Line<i32>>], crossings: &[Crossing]) -> String {
+    let origin = Coordinate { x: 0, y: 0 };
+    let paths: Vec<LineString<i32>> = wires
+        .iter()
+        .map(|wire| {
+            let mut points = vec![origin];
+            points.extend(wire.iter().map(|line| line.end));
+            LineString(points)
+        })
+        .collect();
+    let (mut low, mut high) = (origin, origin);
+    for rect in paths.iter().filter_map(|path| path.bounding_rect()) {
+        low = Coordinate {
+            x: low.x.min(rect.min.x),
+            y: low.y.min(rect.min.y),
+        };
+        high = Coordinate {
+            x: high.x.max(rect.max.x),
+            y: high.y.max(rect.max.y),
+        };
+    }
+    // wires can reach both ends of an i32, so the extent needs more room
+    let (low, high) = (
+        (i64::from(low.x), i64::from(low.y)),
+        (i64::from(high.x), i64::from(high.y)),
+    );
+    let span = (high.0 - low.0).max(high.1 - low.1).max(1);
+    let margin = span / 20 + 1;
+    let dot = (span / 400).max(1);
+    let (width, height) = (high.0 - low.0 + 2 * margin, high.1 - low.1 + 2 * margin);
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+        WIDTH,
+        WIDTH * height / width,
+        low.0 - margin,
+        -high.1 - margin,
+        width,
+        height
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"  <rect x="{}" y="{}" width="{}" height="{}" fill="white"/>"#,
+        low.0 - margin,
+        -high.1 - margin,
+        width,
+        height
+    )
+    .unwrap();
+    for (path, colour) in paths.iter().zip(COLOURS.iter().cycle()) {
+        let points: Vec<String> = path.0.iter().map(|c| point(*c)).collect();
+        writeln!(
+            svg,
+            r#"  <polyline points="{}" fill="none" stroke="{}" stroke-width="1.5" vector-effect="non-scaling-stroke"/>"#,
+            points.join(" "),
+            colour
+        )
+        .unwrap();
+    }
+    for crossing in crossings {
+        circle(&mut svg, crossing.point, dot, r#"fill="gray""#);
+    }
+    let ring = |colour| {
+        format!(
+            r#"fill="none" stroke="{}" stroke-width="3" vector-effect="non-scaling-stroke""#,
+            colour
+        )
+    };
+    if let Some(crossing) = closest(crossings) {
+        circle(&mut svg, crossing.point, dot * 4, &ring("blue"));
+    }
+    if let Some(crossing) = quickest(crossings) {
+        circle(&mut svg, crossing.point, dot * 6, &ring("red"));
+    }
+    circle(&mut svg, origin, dot * 2, r#"fill="black""#);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{crossings, wire};
+    use super::*;
+
+    #[test]
+    fn test_svg() {
+        let wires = vec![wire("R8,U5,L5,D3").unwrap(), wire("U7,R6,D4,L4").unwrap()];
+        let found = crossings(&wires);
+        let svg = svg(&wires, &found);
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.contains(r#"viewBox="-1 -8 10 9""#));
+        assert!(svg.contains(r#"points="0,0 8,0 8,-5 3,-5 3,-2""#));
+        assert!(svg.contains(r#"points="0,0 0,-7 6,-7 6,-3 2,-3""#));
+        // two crossings, the closest at (3, 3) and the quickest at (6, 5)
+        assert_eq!(svg.matches(r#"fill="gray""#).count(), 2);
+        assert!(svg.contains(r#"<circle cx="3" cy="-3" r="4" fill="none" stroke="blue""#));
+        assert!(svg.contains(r#"<circle cx="6" cy="-5" r="6" fill="none" stroke="red""#));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn test_far_out_wires() {
+        let wires = vec![
+            wire("R2147483647,U1,L2147483647").unwrap(),
+            wire("D2147483647,D1,L2147483647,L1").unwrap(),
+        ];
+        let svg = svg(&wires, &crossings(&wires));
+        assert!(svg.contains(r#"viewBox="-2362232013 -214748366 4724464025 2576980379"#));
+        assert!(svg.contains(r#"points="0,0 0,2147483647 0,2147483648 -2147483647,2147483648 -2147483648,2147483648""#));
+    }
+}
@@ -0,0 +1,343 @@
+//! Where axis aligned polylines cross, found with a sweep line.
+//!
+//! Horizontal segments go into a set ordered by `y` as the sweep reaches their left end and
+//! come out after their right end, so each vertical segment finds everything it crosses with
+//! one range query. Segments lying on the same line can share whole stretches; those are found
+//! separately by sorting each line's segments by where they start. Both keep one set per wire
+//! and only look in the other wires' sets, so a wire running over itself costs nothing extra.
+//! For n segments across w wires with k shared points, that's O((n w + k) log n).
+use geo::{Coordinate, Line};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
+
+/// Where two wires cross, besides the origin they both leave from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Crossing {
+    pub point: Coordinate<i32>,
+    /// Which two wires, the lower numbered first.
+    pub wires: (usize, usize),
+    /// How far along each wire the point is. Wide enough that no wire of `i32` segments can
+    /// overflow it.
+    pub steps: (i64, i64),
+}
+
+struct Segment {
+    wire: usize,
+    /// Length of the wire before this segment.
+    before: i64,
+    line: Line<i32>,
+}
+
+impl Segment {
+    fn steps_to(&self, point: Coordinate<i32>) -> i64 {
+        self.before + distance(self.line.start, point)
+    }
+
+    fn is_horizontal(&self) -> bool {
+        self.line.start.y == self.line.end.y
+    }
+
+    /// The segment along the axis it runs on, and its position on the other one.
+    fn span(&self) -> (i32, i32, i32) {
+        let (start, end) = (self.line.start, self.line.end);
+        if self.is_horizontal() {
+            (start.x.min(end.x), start.x.max(end.x), start.y)
+        } else {
+            (start.y.min(end.y), start.y.max(end.y), start.x)
+        }
+    }
+}
+
+struct Sweep<'a> {
+    segments: &'a [Segment],
+    crossings: Vec<Crossing>,
+}
+
+impl<'a> Sweep<'a> {
+    fn record(&mut self, point: Coordinate<i32>, i: usize, j: usize) {
+        let (a, b) = (&self.segments[i], &self.segments[j]);
+        // the origin doesn't count, and a segment starts where the one before it ended, which
+        // has been counted already
+        let origin = Coordinate { x: 0, y: 0 };
+        debug_assert_ne!(a.wire, b.wire);
+        if point == origin || point == a.line.start || point == b.line.start {
+            return;
+        }
+        let (a, b) = if a.wire < b.wire { (a, b) } else { (b, a) };
+        self.crossings.push(Crossing {
+            point,
+            wires: (a.wire, b.wire),
+            steps: (a.steps_to(point), b.steps_to(point)),
+        });
+    }
+
+    fn wires(&self) -> usize {
+        self.segments.iter().map(|s| s.wire + 1).max().unwrap_or(0)
+    }
+
+    /// Horizontal segments against vertical ones.
+    fn perpendicular(&mut self) {
+        // at one x, insert before querying before removing
+        let mut events: Vec<(i32, u8, usize)> = Vec::new();
+        for (i, segment) in self.segments.iter().enumerate() {
+            let (low, high, _) = segment.span();
+            if segment.is_horizontal() {
+                events.push((low, 0, i));
+                events.push((high, 2, i));
+            } else {
+                events.push((segment.line.start.x, 1, i));
+            }
+        }
+        events.sort_unstable();
+
+        let mut active: Vec<BTreeSet<(i32, usize)>> = vec![BTreeSet::new(); self.wires()];
+        for (x, kind, i) in events {
+            let (low, high, y) = self.segments[i].span();
+            let wire = self.segments[i].wire;
+            match kind {
+                0 => {
+                    active[wire].insert((y, i));
+                }
+                1 => {
+                    let crossed: Vec<(i32, usize)> = active
+                        .iter()
+                        .enumerate()
+                        .filter(|(other, _)| *other != wire)
+                        .flat_map(|(_, set)| set.range((low, 0)..=(high, usize::MAX)))
+                        .cloned()
+                        .collect();
+                    for (y, j) in crossed {
+                        self.record(Coordinate { x, y }, j, i);
+                    }
+                }
+                _ => {
+                    active[wire].remove(&(y, i));
+                }
+            }
+        }
+    }
+
+    /// Segments lying on the same line, horizontal or not.
+    fn collinear(&mut self, horizontal: bool) {
+        let mut lines: HashMap<i32, Vec<(i32, i32, usize)>> = HashMap::new();
+        for (i, segment) in self.segments.iter().enumerate() {
+            // zero length segments count as horizontal, the perpendicular pass sees them too
+            if segment.is_horizontal() == horizontal {
+                let (low, high, at) = segment.span();
+                lines.entry(at).or_default().push((low, high, i));
+            }
+        }
+        let wires = self.wires();
+        for (at, mut spans) in lines {
+            spans.sort_unstable();
+            // everything left in a heap once the ones that ended are popped overlaps
+            let mut open: Vec<BinaryHeap<Reverse<(i32, usize)>>> = vec![BinaryHeap::new(); wires];
+            for (low, high, i) in spans {
+                let wire = self.segments[i].wire;
+                let mut overlapping = Vec::new();
+                for (other, heap) in open.iter_mut().enumerate() {
+                    while heap.peek().is_some_and(|Reverse((end, _))| *end < low) {
+                        heap.pop();
+                    }
+                    if other != wire {
+                        overlapping.extend(heap.iter().map(|r| r.0));
+                    }
+                }
+                for (end, j) in overlapping {
+                    for along in low..=high.min(end) {
+                        let point = if horizontal {
+                            Coordinate { x: along, y: at }
+                        } else {
+                            Coordinate { x: at, y: along }
+                        };
+                        self.record(point, j, i);
+                    }
+                }
+                open[wire].push(Reverse((high, i)));
+            }
+        }
+    }
+}
+
+/// Steps between two points on the same axis aligned line.
+fn distance(a: Coordinate<i32>, b: Coordinate<i32>) -> i64 {
+    (i64::from(b.x) - i64::from(a.x)).abs() + (i64::from(b.y) - i64::from(a.y)).abs()
+}
+
+fn segments(wires: &[&[Line<i32>]]) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    for (wire, lines) in wires.iter().enumerate() {
+        let mut before = 0;
+        for line in lines.iter() {
+            segments.push(Segment {
+                wire,
+                before,
+                line: *line,
+            });
+            before += distance(line.start, line.end);
+        }
+    }
+    segments
+}
+
+/// Every point two wires share, ordered by x and then y. A point the wires pass more than
+/// once comes up once per pair of visits.
+pub fn crossings(a: &[Line<i32>], b: &[Line<i32>]) -> Vec<Crossing> {
+    all(&[a, b])
+}
+
+/// Every point shared by some pair of the wires, numbered by their place in `wires`. Ordered by
+/// x, then y, then the pair; a point three wires pass comes up once for each of the three pairs.
+pub fn all(wires: &[&[Line<i32>]]) -> Vec<Crossing> {
+    let segments = segments(wires);
+    let mut sweep = Sweep {
+        segments: &segments,
+        crossings: Vec::new(),
+    };
+    sweep.perpendicular();
+    sweep.collinear(true);
+    sweep.collinear(false);
+    let mut crossings = sweep.crossings;
+    crossings.sort_unstable_by_key(|c| (c.point.x, c.point.y, c.wires, c.steps));
+    crossings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::devices::Rng;
+
+    fn line(x0: i32, y0: i32, x1: i32, y1: i32) -> Line<i32> {
+        Line {
+            start: Coordinate { x: x0, y: y0 },
+            end: Coordinate { x: x1, y: y1 },
+        }
+    }
+
+    fn random_wire(rng: &mut Rng, moves: usize) -> Vec<Line<i32>> {
+        let mut at = Coordinate { x: 0, y: 0 };
+        let mut wire = Vec::new();
+        for _ in 0..moves {
+            let distance = rng.below(7) as i32 - 3;
+            let end = match rng.below(2) {
+                0 => Coordinate {
+                    x: at.x + distance,
+                    y: at.y,
+                },
+                _ => Coordinate {
+                    x: at.x,
+                    y: at.y + distance,
+                },
+            };
+            wire.push(Line { start: at, end });
+            at = end;
+        }
+        wire
+    }
+
+    /// Every grid point of every segment, one by one.
+    fn brute_force(a: &[Line<i32>], b: &[Line<i32>]) -> Vec<Crossing> {
+        let points = |wire: &[Line<i32>]| {
+            let mut points = Vec::new();
+            let mut steps = 0i64;
+            for line in wire {
+                let (dx, dy) = (line.end.x - line.start.x, line.end.y - line.start.y);
+                for n in 1..=dx.abs() + dy.abs() {
+                    let point = Coordinate {
+                        x: line.start.x + dx.signum() * n,
+                        y: line.start.y + dy.signum() * n,
+                    };
+                    points.push((point, steps + i64::from(n)));
+                }
+                steps += i64::from(dx.abs() + dy.abs());
+            }
+            points
+        };
+        let mut crossings = Vec::new();
+        for (p, sa) in points(a) {
+            for (q, sb) in points(b) {
+                if p == q && p != (Coordinate { x: 0, y: 0 }) {
+                    crossings.push(Crossing {
+                        point: p,
+                        wires: (0, 1),
+                        steps: (sa, sb),
+                    });
+                }
+            }
+        }
+        crossings.sort_unstable_by_key(|c| (c.point.x, c.point.y, c.steps));
+        crossings
+    }
+
+    #[test]
+    fn test_perpendicular() {
+        let a = [line(0, 0, 10, 0), line(10, 0, 10, 10)];
+        let b = [
+            line(0, 0, 0, 5),
+            line(0, 5, 20, 5),
+            line(20, 5, 20, -5),
+            line(20, -5, 5, -5),
+        ];
+        let crossings = crossings(&a, &b);
+        assert_eq!(
+            crossings,
+            vec![Crossing {
+                point: Coordinate { x: 10, y: 5 },
+                wires: (0, 1),
+                steps: (15, 15),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_collinear_overlap() {
+        let a = [line(0, 0, 0, 3), line(0, 3, 8, 3)];
+        let b = [line(0, 0, 6, 0), line(6, 0, 6, 3), line(6, 3, 2, 3)];
+        let points: Vec<(i32, i32, i64, i64)> = crossings(&a, &b)
+            .iter()
+            .map(|c| (c.point.x, c.point.y, c.steps.0, c.steps.1))
+            .collect();
+        assert_eq!(
+            points,
+            vec![
+                (2, 3, 5, 13),
+                (3, 3, 6, 12),
+                (4, 3, 7, 11),
+                (5, 3, 8, 10),
+                (6, 3, 9, 9)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let mut rng = Rng::new(47);
+        for _ in 0..200 {
+            let a = random_wire(&mut rng, 12);
+            let b = random_wire(&mut rng, 12);
+            assert_eq!(crossings(&a, &b), brute_force(&a, &b), "{:?} {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_many_wires() {
+        let mut rng = Rng::new(50);
+        for _ in 0..50 {
+            let wires: Vec<Vec<Line<i32>>> = (0..4).map(|_| random_wire(&mut rng, 10)).collect();
+            let lines: Vec<&[Line<i32>]> = wires.iter().map(|w| &w[..]).collect();
+            // the same as every pair on its own
+            let mut expected = Vec::new();
+            for i in 0..wires.len() {
+                for j in i + 1..wires.len() {
+                    expected.extend(
+                        brute_force(&wires[i], &wires[j])
+                            .into_iter()
+                            .map(|c| Crossing { wires: (i, j), ..c }),
+                    );
+                }
+            }
+            expected.sort_unstable_by_key(|c| (c.point.x, c.point.y, c.wires, c.steps));
+            assert_eq!(all(&lines), expected, "{:?}", wires);
+        }
+    }
+}
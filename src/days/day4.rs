@@ -0,0 +1,148 @@
+//! Secure Container: counting six digit passwords in a range.
+//!
+//! Each part is a [`rules::Predicate`], counted over the range by the digit DP in
+//! [`sequences::count`], so ranges far wider than six digits are no slower.
+//! [`search`](rules::Predicate::search) lists the same passwords one by one.
+use self::rules::{between, length, monotonic, some_run, Order, Predicate};
+use super::Solution;
+use failure::{err_msg, Fallible, ResultExt};
+use std::ops::RangeInclusive;
+
+pub mod rules;
+pub mod sequences;
+
+const WIDTH: usize = 6;
+
+const NOT_A_RANGE: &str = "the input should be a range like 100000-999999";
+
+fn password(range: &RangeInclusive<u64>) -> Predicate {
+    between(range.clone())
+        .and(length(WIDTH))
+        .and(monotonic(Order::NonDecreasing))
+}
+
+/// Some digit appears at least twice in a row.
+pub fn part1(range: &RangeInclusive<u64>) -> Predicate {
+    password(range).and(some_run(2..=usize::MAX))
+}
+
+/// Some digit appears exactly twice in a row, not as part of a longer run.
+pub fn part2(range: &RangeInclusive<u64>) -> Predicate {
+    password(range).and(some_run(2..=2))
+}
+
+pub struct Puzzle;
+
+impl Solution for Puzzle {
+    type Input = RangeInclusive<u64>;
+    type Answer = u64;
+
+    /// The input is the range as `start-end`.
+    fn parse(input: &str) -> Fallible<RangeInclusive<u64>> {
+        let mut bounds = input.trim().splitn(2, '-');
+        let mut bound = || -> Fallible<u64> {
+            let bound = bounds.next().ok_or_else(|| err_msg(NOT_A_RANGE))?;
+            Ok(bound.parse::<u64>().context(NOT_A_RANGE)?)
+        };
+        Ok(bound()?..=bound()?)
+    }
+
+    fn part1(range: &RangeInclusive<u64>) -> Fallible<u64> {
+        Ok(part1(range).count(range.clone()))
+    }
+
+    fn part2(range: &RangeInclusive<u64>) -> Fallible<u64> {
+        Ok(part2(range).count(range.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rules::runs;
+    use super::sequences::digits;
+    use super::*;
+
+    fn check(x: u64, rule: &Predicate) -> bool {
+        rule.matches(&digits(x))
+    }
+
+    fn is_password(x: u64, rule: fn(&RangeInclusive<u64>) -> Predicate) -> bool {
+        check(x, &rule(&(0..=u64::MAX)))
+    }
+
+    #[test]
+    fn test_dupe_check() {
+        let pair = some_run(2..=usize::MAX);
+        let exact = some_run(2..=2);
+        for rule in [&pair, &exact] {
+            assert!(check(113456, rule));
+            assert!(check(122456, rule));
+            assert!(check(123446, rule));
+            assert!(check(123455, rule));
+            assert!(!check(123456, rule));
+        }
+        // pairs have to be next to each other
+        assert!(!check(121345, &pair));
+        assert!(!check(121345, &exact));
+        // a run of three is a pair for part 1 only
+        assert!(check(123444, &pair));
+        assert!(!check(123444, &exact));
+        assert!(check(111122, &exact));
+    }
+
+    #[test]
+    fn test_only_increases() {
+        let rule = monotonic(Order::NonDecreasing);
+        assert!(check(123456, &rule));
+        assert!(check(123444, &rule));
+        assert!(check(111111, &rule));
+        assert!(!check(654321, &rule));
+    }
+
+    #[test]
+    fn test_runs() {
+        assert_eq!(runs(&digits(111144)), vec![4, 2]);
+        assert_eq!(runs(&digits(112333)), vec![2, 1, 3]);
+        assert_eq!(runs(&[]), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_puzzle_examples() {
+        assert!(is_password(111111, part1));
+        assert!(!is_password(223450, part1));
+        assert!(!is_password(123789, part1));
+        assert!(is_password(112233, part2));
+        assert!(!is_password(123444, part2));
+        assert!(is_password(111122, part2));
+        assert!(!is_password(11122, part2));
+
+        let range = Puzzle::parse("111110-111123\n").unwrap();
+        assert_eq!(Puzzle::part1(&range).unwrap(), 11);
+        assert_eq!(Puzzle::part2(&range).unwrap(), 1);
+        assert!(Puzzle::parse("111110").is_err());
+        let error = Puzzle::parse("R8,U5,L5,D3").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the input should be a range like 100000-999999"
+        );
+
+        let listed: Vec<Vec<u8>> = part2(&range).search(range).collect();
+        assert_eq!(listed, vec![digits(111122)]);
+    }
+
+    #[test]
+    fn test_counting_agrees_with_listing() {
+        let range = 172_851..=675_869;
+        assert_eq!(Puzzle::part1(&range).unwrap(), 1660);
+        assert_eq!(Puzzle::part2(&range).unwrap(), 1135);
+        assert_eq!(part1(&range).search(range.clone()).count(), 1660);
+        assert_eq!(part2(&range).search(range.clone()).count(), 1135);
+        // far past six digits: every non-decreasing number has a pair except the 511 made of
+        // distinct digits, and there are C(28, 9) - 1 non-decreasing numbers below 10^19
+        let pair = monotonic(Order::NonDecreasing).and(some_run(2..=usize::MAX));
+        assert_eq!(
+            pair.count(1..=9_999_999_999_999_999_999),
+            6_906_900 - 1 - 511
+        );
+    }
+}
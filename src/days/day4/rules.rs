@@ -0,0 +1,462 @@
+//! Password rules as expressions over a number's digits.
+//!
+//! The building blocks are [`length`], [`monotonic`], [`some_run`], [`every_run`] and
+//! [`between`], combined with [`and`](Predicate::and), [`or`](Predicate::or) and `!`. A
+//! predicate is a [`Rule`], read one digit at a time, which is how it
+//! [`matches`](Predicate::matches) a number too. It can [`search`](Predicate::search) or
+//! [`count`](Predicate::count) a range itself: when it only allows digits that never decrease it
+//! walks [`NonDecreasing`] candidates or counts them with the digit DP, otherwise it looks at
+//! every number in the range.
+use super::sequences::{self, digits, NonDecreasing, Rule};
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::ops::{Not, RangeInclusive};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Order {
+    NonDecreasing,
+    Increasing,
+    NonIncreasing,
+    Decreasing,
+}
+
+impl Order {
+    fn allows(self, a: u8, b: u8) -> bool {
+        match self {
+            Order::NonDecreasing => a <= b,
+            Order::Increasing => a < b,
+            Order::NonIncreasing => a >= b,
+            Order::Decreasing => a > b,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    Length(usize),
+    Monotonic(Order),
+    /// Some run of equal adjacent digits has a length in the range.
+    SomeRun(RangeInclusive<usize>),
+    /// Every run of equal adjacent digits does.
+    EveryRun(RangeInclusive<usize>),
+    /// The number itself is in the range.
+    Between(RangeInclusive<u64>),
+    And(Box<Predicate>, Box<Predicate>),
+    Or(Box<Predicate>, Box<Predicate>),
+    Not(Box<Predicate>),
+}
+
+pub fn length(n: usize) -> Predicate {
+    Predicate::Length(n)
+}
+
+pub fn monotonic(order: Order) -> Predicate {
+    Predicate::Monotonic(order)
+}
+
+pub fn some_run(lengths: RangeInclusive<usize>) -> Predicate {
+    Predicate::SomeRun(lengths)
+}
+
+pub fn every_run(lengths: RangeInclusive<usize>) -> Predicate {
+    Predicate::EveryRun(lengths)
+}
+
+pub fn between(range: RangeInclusive<u64>) -> Predicate {
+    Predicate::Between(range)
+}
+
+/// Lengths of the runs of equal adjacent digits, so `112333` gives `[2, 1, 3]`.
+pub fn runs(digits: &[u8]) -> Vec<usize> {
+    let mut runs: Vec<usize> = Vec::new();
+    for (i, digit) in digits.iter().enumerate() {
+        match runs.last_mut() {
+            Some(run) if digits[i - 1] == *digit => *run += 1,
+            _ => runs.push(1),
+        }
+    }
+    runs
+}
+
+/// How far a [`Predicate`] has got through a number's digits.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Progress {
+    width: usize,
+    /// Digits read so far.
+    read: usize,
+    last: Option<u8>,
+    /// How many times in a row `last` came.
+    run: usize,
+    /// One per building block, left to right.
+    leaves: Vec<Leaf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Leaf {
+    /// Nothing to remember, the width is enough.
+    Length,
+    /// Whether the digits are in order so far, whether some finished run passed, or whether
+    /// every finished run did.
+    Holds(bool),
+    /// How the digits so far compare with the bounds' digits in the same places.
+    Between(Ordering, Ordering),
+}
+
+/// Digit `pos` of `bound` written with `width` digits, counting leading zeros.
+fn bound_digit(bound: u64, width: usize, pos: usize) -> u8 {
+    10u64
+        .checked_pow((width - 1 - pos) as u32)
+        .map_or(0, |p| (bound / p % 10) as u8)
+}
+
+/// Whether `width` digits can't reach `bound`.
+fn too_short(bound: u64, width: usize) -> bool {
+    10u64.checked_pow(width as u32).is_some_and(|p| bound >= p)
+}
+
+impl Predicate {
+    pub fn and(self, other: Predicate) -> Predicate {
+        Predicate::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: Predicate) -> Predicate {
+        Predicate::Or(Box::new(self), Box::new(other))
+    }
+
+    pub fn matches(&self, digits: &[u8]) -> bool {
+        let state = digits
+            .iter()
+            .fold(self.start(digits.len()), |state, d| self.push(&state, *d));
+        self.accepts(&state)
+    }
+
+    fn leaves(&self, width: usize, leaves: &mut Vec<Leaf>) {
+        match self {
+            Predicate::Length(_) => leaves.push(Leaf::Length),
+            Predicate::Monotonic(_) | Predicate::EveryRun(_) => leaves.push(Leaf::Holds(true)),
+            Predicate::SomeRun(_) => leaves.push(Leaf::Holds(false)),
+            Predicate::Between(range) => {
+                let order = |bound| {
+                    if too_short(bound, width) {
+                        Ordering::Less
+                    } else {
+                        Ordering::Equal
+                    }
+                };
+                leaves.push(Leaf::Between(order(*range.start()), order(*range.end())));
+            }
+            Predicate::And(a, b) | Predicate::Or(a, b) => {
+                a.leaves(width, leaves);
+                b.leaves(width, leaves);
+            }
+            Predicate::Not(a) => a.leaves(width, leaves),
+        }
+    }
+
+    /// Feeds `digit` to the building blocks from `leaves[*i]` on, moving `i` past them.
+    fn step(&self, state: &Progress, digit: u8, leaves: &mut [Leaf], i: &mut usize) {
+        match self {
+            Predicate::And(a, b) | Predicate::Or(a, b) => {
+                a.step(state, digit, leaves, i);
+                b.step(state, digit, leaves, i);
+                return;
+            }
+            Predicate::Not(a) => {
+                a.step(state, digit, leaves, i);
+                return;
+            }
+            _ => {}
+        }
+        // the run before this digit, if it just ended
+        let ended = match state.last {
+            Some(last) if last != digit => Some(state.run),
+            _ => None,
+        };
+        let leaf = &mut leaves[*i];
+        *i += 1;
+        *leaf = match (self, *leaf) {
+            (Predicate::Monotonic(order), Leaf::Holds(holds)) => {
+                Leaf::Holds(holds && state.last.is_none_or(|last| order.allows(last, digit)))
+            }
+            (Predicate::SomeRun(lengths), Leaf::Holds(found)) => {
+                Leaf::Holds(found || ended.is_some_and(|run| lengths.contains(&run)))
+            }
+            (Predicate::EveryRun(lengths), Leaf::Holds(all)) => {
+                Leaf::Holds(all && ended.is_none_or(|run| lengths.contains(&run)))
+            }
+            (Predicate::Between(range), Leaf::Between(low, high)) => {
+                let compare = |order: Ordering, bound| {
+                    order.then_with(|| digit.cmp(&bound_digit(bound, state.width, state.read)))
+                };
+                Leaf::Between(compare(low, *range.start()), compare(high, *range.end()))
+            }
+            (_, leaf) => leaf,
+        };
+    }
+
+    fn holds(&self, state: &Progress, i: &mut usize) -> bool {
+        // the last run hasn't been checked yet
+        let last_run = Some(state.run).filter(|_| state.last.is_some());
+        let leaf = state.leaves[*i];
+        let holds = match (self, leaf) {
+            (Predicate::Length(n), _) => state.width == *n,
+            (Predicate::Monotonic(_), Leaf::Holds(holds)) => holds,
+            (Predicate::SomeRun(lengths), Leaf::Holds(found)) => {
+                found || last_run.is_some_and(|run| lengths.contains(&run))
+            }
+            (Predicate::EveryRun(lengths), Leaf::Holds(all)) => {
+                all && last_run.is_none_or(|run| lengths.contains(&run))
+            }
+            (Predicate::Between(_), Leaf::Between(low, high)) => {
+                low != Ordering::Less && high != Ordering::Greater
+            }
+            (Predicate::And(a, b), _) => {
+                let a = a.holds(state, i);
+                return b.holds(state, i) && a;
+            }
+            (Predicate::Or(a, b), _) => {
+                let a = a.holds(state, i);
+                return b.holds(state, i) || a;
+            }
+            (Predicate::Not(a), _) => return !a.holds(state, i),
+            _ => unreachable!("leaves out of step with the predicate"),
+        };
+        *i += 1;
+        holds
+    }
+
+    /// Whether everything this matches has digits that never decrease.
+    fn never_decreases(&self) -> bool {
+        match self {
+            Predicate::Monotonic(order) => {
+                matches!(order, Order::NonDecreasing | Order::Increasing)
+            }
+            Predicate::And(a, b) => a.never_decreases() || b.never_decreases(),
+            Predicate::Or(a, b) => a.never_decreases() && b.never_decreases(),
+            _ => false,
+        }
+    }
+
+    /// The digits of every number in `range` this matches, in order.
+    pub fn search<'a>(
+        &'a self,
+        range: RangeInclusive<u64>,
+    ) -> Box<dyn Iterator<Item = Vec<u8>> + 'a> {
+        let candidates: Box<dyn Iterator<Item = Vec<u8>>> = if self.never_decreases() {
+            Box::new(widths(&range).flat_map(move |width| NonDecreasing::new(width, range.clone())))
+        } else {
+            Box::new(range.map(digits))
+        };
+        Box::new(candidates.filter(move |digits| self.matches(digits)))
+    }
+
+    /// How many numbers in `range` this matches, without listing them when it can.
+    pub fn count(&self, range: RangeInclusive<u64>) -> u64 {
+        if self.never_decreases() {
+            widths(&range)
+                .map(|width| sequences::count(width, range.clone(), self))
+                .sum()
+        } else {
+            self.search(range).count() as u64
+        }
+    }
+}
+
+fn widths(range: &RangeInclusive<u64>) -> RangeInclusive<usize> {
+    digits(*range.start()).len()..=digits(*range.end()).len()
+}
+
+impl Rule for Predicate {
+    type State = Progress;
+
+    fn start(&self, width: usize) -> Progress {
+        let mut leaves = Vec::new();
+        self.leaves(width, &mut leaves);
+        Progress {
+            width,
+            read: 0,
+            last: None,
+            run: 0,
+            leaves,
+        }
+    }
+
+    fn push(&self, state: &Progress, digit: u8) -> Progress {
+        let mut leaves = state.leaves.clone();
+        self.step(state, digit, &mut leaves, &mut 0);
+        let run = if state.last == Some(digit) {
+            state.run + 1
+        } else {
+            1
+        };
+        Progress {
+            width: state.width,
+            read: state.read + 1,
+            last: Some(digit),
+            run,
+            leaves,
+        }
+    }
+
+    fn accepts(&self, state: &Progress) -> bool {
+        self.holds(state, &mut 0)
+    }
+}
+
+impl Not for Predicate {
+    type Output = Predicate;
+
+    fn not(self) -> Predicate {
+        Predicate::Not(Box::new(self))
+    }
+}
+
+fn lengths(w: &mut Formatter, range: &RangeInclusive<usize>) -> FmtResult {
+    match (*range.start(), *range.end()) {
+        (a, b) if a == b => write!(w, "{}", a),
+        (a, usize::MAX) => write!(w, "{}+", a),
+        (a, b) => write!(w, "{}-{}", a, b),
+    }
+}
+
+impl Display for Predicate {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        match self {
+            Predicate::Length(n) => write!(w, "{} digits", n),
+            Predicate::Monotonic(order) => write!(w, "{:?}", order),
+            Predicate::SomeRun(range) => {
+                write!(w, "some run of ")?;
+                lengths(w, range)
+            }
+            Predicate::EveryRun(range) => {
+                write!(w, "every run of ")?;
+                lengths(w, range)
+            }
+            Predicate::Between(range) => write!(w, "{}-{}", range.start(), range.end()),
+            Predicate::And(a, b) => write!(w, "({} and {})", a, b),
+            Predicate::Or(a, b) => write!(w, "({} or {})", a, b),
+            Predicate::Not(a) => write!(w, "not {}", a),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(rule: &Predicate, x: u64) -> bool {
+        rule.matches(&digits(x))
+    }
+
+    /// Straight from the definitions, to check the digit by digit version against.
+    fn expected(rule: &Predicate, digits: &[u8]) -> bool {
+        let value = digits.iter().fold(0u128, |x, d| x * 10 + *d as u128);
+        match rule {
+            Predicate::Length(n) => digits.len() == *n,
+            Predicate::Monotonic(order) => digits.windows(2).all(|p| order.allows(p[0], p[1])),
+            Predicate::SomeRun(lengths) => runs(digits).iter().any(|run| lengths.contains(run)),
+            Predicate::EveryRun(lengths) => runs(digits).iter().all(|run| lengths.contains(run)),
+            Predicate::Between(range) => {
+                *range.start() as u128 <= value && value <= *range.end() as u128
+            }
+            Predicate::And(a, b) => expected(a, digits) && expected(b, digits),
+            Predicate::Or(a, b) => expected(a, digits) || expected(b, digits),
+            Predicate::Not(a) => !expected(a, digits),
+        }
+    }
+
+    #[test]
+    fn test_building_blocks() {
+        assert!(check(&length(3), 123));
+        assert!(!check(&length(3), 1234));
+        assert!(check(&monotonic(Order::NonDecreasing), 1123));
+        assert!(!check(&monotonic(Order::Increasing), 1123));
+        assert!(check(&monotonic(Order::Decreasing), 9631));
+        assert!(check(&some_run(3..=3), 122233));
+        assert!(!check(&every_run(1..=2), 122233));
+        assert!(check(&between(10..=20), 15));
+        assert!(!check(&between(10..=20), 21));
+    }
+
+    #[test]
+    fn test_combinators() {
+        let pair = some_run(2..=usize::MAX);
+        let exact = some_run(2..=2);
+        // part 2 is part 1 without the numbers whose only pairs are in longer runs
+        let long_only = pair.clone().and(!exact.clone());
+        assert!(check(&long_only, 123444));
+        assert!(!check(&long_only, 111122));
+        assert!(check(&exact.clone().or(length(1)), 7));
+        assert_eq!(
+            long_only.to_string(),
+            "(some run of 2+ and not some run of 2)"
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let rule = monotonic(Order::Increasing).and(between(1..=1000));
+        // digits from 1-9 without repeats: 9 + C(9, 2) + C(9, 3)
+        assert_eq!(rule.search(1..=1000).count(), 9 + 36 + 84);
+        // without monotonicity it looks at every number
+        let rule = every_run(2..=2);
+        let found: Vec<Vec<u8>> = rule.search(1..=1200).collect();
+        assert_eq!(found.len(), 9 + 9);
+        assert_eq!(found[9], vec![1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn test_search_agrees_with_every_number() {
+        let rules = [
+            monotonic(Order::NonDecreasing),
+            monotonic(Order::Increasing).and(some_run(1..=1)),
+            monotonic(Order::NonDecreasing).and(!between(5..=40)),
+        ];
+        for rule in rules.iter() {
+            for range in [0..=9, 0..=0, 0..=2_000, 8..=123] {
+                let every: Vec<Vec<u8>> = range
+                    .clone()
+                    .map(digits)
+                    .filter(|d| rule.matches(d))
+                    .collect();
+                assert_eq!(rule.search(range.clone()).collect::<Vec<_>>(), every);
+                assert_eq!(rule.count(range), every.len() as u64, "{}", rule);
+            }
+        }
+        assert_eq!(monotonic(Order::NonDecreasing).search(0..=9).count(), 10);
+    }
+
+    #[test]
+    fn test_digit_by_digit() {
+        let rules = [
+            some_run(2..=3).and(!every_run(1..=1)),
+            monotonic(Order::NonIncreasing).or(between(300..=4500)),
+            !between(0..=77).and(length(2)),
+            every_run(2..=usize::MAX).or(monotonic(Order::Increasing)),
+        ];
+        for rule in rules.iter() {
+            for x in 0..=20_000 {
+                assert_eq!(check(rule, x), expected(rule, &digits(x)), "{} {}", rule, x);
+            }
+            // leading zeros and widths past what a u64 can hold
+            for digits in [vec![0, 0, 4, 5], vec![1; 22], vec![]] {
+                assert_eq!(rule.matches(&digits), expected(rule, &digits), "{}", rule);
+            }
+        }
+    }
+
+    #[test]
+    fn test_count() {
+        let rules = [
+            monotonic(Order::NonDecreasing).and(some_run(2..=2).or(between(1234..=5678))),
+            monotonic(Order::Increasing).and(!some_run(1..=1).or(length(4))),
+            every_run(2..=2),
+        ];
+        for rule in rules.iter() {
+            for range in [0..=99_999, 1_500..=123_456, 7..=7] {
+                let listed = rule.search(range.clone()).count() as u64;
+                assert_eq!(rule.count(range), listed, "{}", rule);
+            }
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! Non-decreasing digit sequences, listed one by one or counted without listing them.
+//!
+//! Both work on numbers of exactly `width` digits inside an inclusive range. [`NonDecreasing`]
+//! skips straight from one candidate to the next, which is plenty for six digits. [`count`]
+//! is a digit DP for when even that is too many: it walks the digits from the left, tracking
+//! the last digit, whether we are still pinned to the upper bound, and a [`Rule`]'s state,
+//! so its cost depends on the width and not on how wide the range is. The rules themselves are
+//! [`Predicate`](super::rules::Predicate)s.
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::RangeInclusive;
+
+/// A check on a digit sequence, fed one digit at a time. The state has to stay small for the
+/// DP to be worth anything.
+pub trait Rule {
+    type State: Clone + Eq + Hash;
+
+    /// The state before any of `width` digits.
+    fn start(&self, width: usize) -> Self::State;
+    fn push(&self, state: &Self::State, digit: u8) -> Self::State;
+    fn accepts(&self, state: &Self::State) -> bool;
+}
+
+/// Accepts everything, to count every non-decreasing sequence.
+pub struct Any;
+
+impl Rule for Any {
+    type State = ();
+
+    fn start(&self, _width: usize) {}
+
+    fn push(&self, _state: &(), _digit: u8) {}
+
+    fn accepts(&self, _state: &()) -> bool {
+        true
+    }
+}
+
+/// The decimal digits of `x`, most significant first.
+pub fn digits(x: u64) -> Vec<u8> {
+    x.to_string().bytes().map(|b| b - b'0').collect()
+}
+
+/// `x` as exactly `width` digits, leading zeros and all.
+fn padded(x: u64, width: usize) -> Vec<u8> {
+    let mut digits = vec![0; width];
+    let mut x = x;
+    for digit in digits.iter_mut().rev() {
+        *digit = (x % 10) as u8;
+        x /= 10;
+    }
+    digits
+}
+
+/// Wide enough for twenty 9s, which don't fit a `u64`.
+fn value(digits: &[u8]) -> u128 {
+    digits.iter().fold(0, |x, d| x * 10 + *d as u128)
+}
+
+/// The part of `range` where numbers have exactly `width` digits, if any. Zero is written with
+/// one digit, like [`digits`] does.
+fn clamp(width: usize, range: &RangeInclusive<u64>) -> Option<(u64, u64)> {
+    let low = match width {
+        0 => return None,
+        1 => 0,
+        _ => 10u64.checked_pow(width as u32 - 1)?,
+    };
+    let high = 10u64.checked_pow(width as u32).map_or(u64::MAX, |x| x - 1);
+    let (start, end) = (*range.start().max(&low), *range.end().min(&high));
+    if start <= end {
+        Some((start, end))
+    } else {
+        None
+    }
+}
+
+/// Every `width` digit number in a range whose digits never decrease, in order.
+pub struct NonDecreasing {
+    next: Option<Vec<u8>>,
+    end: u64,
+}
+
+impl NonDecreasing {
+    pub fn new(width: usize, range: RangeInclusive<u64>) -> NonDecreasing {
+        let (next, end) = match clamp(width, &range) {
+            Some((start, end)) => {
+                // the smallest candidate at or above start: once a digit drops, repeat the one
+                // before it to the end
+                let mut next = padded(start, width);
+                if let Some(i) = (1..width).find(|i| next[*i] < next[i - 1]) {
+                    let fill = next[i - 1];
+                    next[i..].iter_mut().for_each(|d| *d = fill);
+                }
+                (Some(next), end)
+            }
+            None => (None, 0),
+        };
+        NonDecreasing { next, end }
+    }
+}
+
+impl Iterator for NonDecreasing {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        let current = self
+            .next
+            .take()
+            .filter(|digits| value(digits) <= self.end as u128)?;
+        // bump the last digit that isn't a 9 and repeat it to the end
+        if let Some(i) = current.iter().rposition(|d| *d < 9) {
+            let mut next = current.clone();
+            let fill = next[i] + 1;
+            next[i..].iter_mut().for_each(|d| *d = fill);
+            self.next = Some(next);
+        }
+        Some(current)
+    }
+}
+
+struct Counter<'a, R: Rule> {
+    rule: &'a R,
+    bound: Vec<u8>,
+    memo: HashMap<(usize, u8, R::State, bool), u64>,
+}
+
+impl<'a, R: Rule> Counter<'a, R> {
+    fn go(&mut self, pos: usize, min: u8, state: R::State, tight: bool) -> u64 {
+        if pos == self.bound.len() {
+            return self.rule.accepts(&state) as u64;
+        }
+        let key = (pos, min, state, tight);
+        if let Some(count) = self.memo.get(&key) {
+            return *count;
+        }
+        let max = if tight { self.bound[pos] } else { 9 };
+        let count = (min..=max)
+            .map(|d| {
+                let next = self.rule.push(&key.2, d);
+                self.go(pos + 1, d, next, tight && d == max)
+            })
+            .sum();
+        self.memo.insert(key, count);
+        count
+    }
+}
+
+/// Non-decreasing `width` digit sequences, leading zeros allowed, no bigger than `bound`.
+fn count_up_to<R: Rule>(width: usize, bound: u64, rule: &R) -> u64 {
+    let mut counter = Counter {
+        rule,
+        bound: padded(bound, width),
+        memo: HashMap::new(),
+    };
+    counter.go(0, 0, rule.start(width), true)
+}
+
+/// How many `width` digit numbers in `range` never decrease and satisfy `rule`. The rule sees
+/// `width` digits either way, so sequences with leading zeros cancel out as long as it only
+/// looks at the digits.
+pub fn count<R: Rule>(width: usize, range: RangeInclusive<u64>, rule: &R) -> u64 {
+    match clamp(width, &range) {
+        // sequences with leading zeros are below start, so they cancel out
+        Some((0, end)) => count_up_to(width, end, rule),
+        Some((start, end)) => count_up_to(width, end, rule) - count_up_to(width, start - 1, rule),
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::rules::{between, every_run, some_run, Predicate};
+    use super::*;
+
+    fn brute_force(width: usize, range: RangeInclusive<u64>) -> Vec<Vec<u8>> {
+        range
+            .map(digits)
+            .filter(|d| d.len() == width)
+            .filter(|d| d.windows(2).all(|p| p[0] <= p[1]))
+            .collect()
+    }
+
+    #[test]
+    fn test_enumerates_only_non_decreasing() {
+        for (width, range) in [
+            (3, 100..=999),
+            (3, 178..=561),
+            (2, 0..=1000),
+            (4, 1299..=1300),
+            (1, 0..=9),
+            (1, 0..=0),
+        ] {
+            let expected = brute_force(width, range.clone());
+            assert_eq!(
+                NonDecreasing::new(width, range.clone()).collect::<Vec<_>>(),
+                expected
+            );
+            assert_eq!(count(width, range, &Any), expected.len() as u64);
+        }
+        assert_eq!(NonDecreasing::new(3, 5..=50).count(), 0);
+        let last = NonDecreasing::new(20, u64::MAX - 10..=u64::MAX).last();
+        assert_eq!(last, None);
+        let first = NonDecreasing::new(20, 0..=u64::MAX).next().unwrap();
+        assert_eq!(first, vec![1; 20]);
+    }
+
+    #[test]
+    fn test_count_with_rules() {
+        let rules = [
+            some_run(2..=usize::MAX),
+            some_run(2..=2),
+            every_run(1..=2),
+            // leading zeros count towards the bounds, and cancel out
+            some_run(3..=3).or(between(123_000..=124_000)),
+        ];
+        for range in [100_000..=999_999, 172_851..=675_869, 111_110..=111_123] {
+            let all: Vec<Vec<u8>> = NonDecreasing::new(6, range.clone()).collect();
+            for rule in rules.iter() {
+                let listed = all.iter().filter(|d| rule.matches(d)).count();
+                assert_eq!(count(6, range.clone(), rule), listed as u64, "{}", rule);
+            }
+        }
+    }
+
+    #[test]
+    fn test_wide_ranges() {
+        // non-decreasing sequences of n digits from 1-9 are multisets: C(n + 8, 8)
+        assert_eq!(count(18, 0..=u64::MAX, &Any), 1_562_275);
+        assert_eq!(count(19, 0..=u64::MAX, &Any), 2_220_075);
+        assert_eq!(count(0, 0..=u64::MAX, &Any), 0);
+        assert_eq!(count(21, 0..=u64::MAX, &Any), 0);
+        let pair: Predicate = some_run(2..=usize::MAX);
+        // only 123456789 has no repeated digit
+        assert_eq!(count(9, 0..=999_999_999, &pair), 24_310 - 1);
+    }
+}
@@ -0,0 +1,59 @@
+//! Sunny with a Chance of Asteroids.
+use super::Solution;
+use crate::intcode::controller::{Ending, Scripted, Session};
+use crate::intcode::parse_program;
+use failure::{err_msg, Fallible};
+
+/// Runs the diagnostic for system `id` and returns the code it ends with.
+fn diagnose(data: &[i32], id: i32) -> Fallible<i32> {
+    let mut session = Session::new(data, Scripted::new(vec![id]));
+    if session.run()? == Ending::Stopped {
+        return Err(err_msg(
+            "the diagnostic wants more input than the system id",
+        ));
+    }
+    session
+        .outputs()
+        .last()
+        .cloned()
+        .ok_or_else(|| err_msg("the diagnostic printed nothing"))
+}
+
+pub struct Puzzle;
+
+impl Solution for Puzzle {
+    type Input = Vec<i32>;
+    type Answer = i32;
+
+    fn parse(input: &str) -> Fallible<Vec<i32>> {
+        Ok(parse_program(input))
+    }
+
+    fn part1(data: &Vec<i32>) -> Fallible<i32> {
+        diagnose(data, 1)
+    }
+
+    fn part2(data: &Vec<i32>) -> Fallible<i32> {
+        diagnose(data, 5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnose() {
+        // 999 below 8, 1000 for 8, 1001 above
+        let data = Puzzle::parse(
+            "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,\
+             20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99",
+        )
+        .unwrap();
+        assert_eq!(diagnose(&data, 7).unwrap(), 999);
+        assert_eq!(diagnose(&data, 8).unwrap(), 1000);
+        assert_eq!(Puzzle::part2(&data).unwrap(), 999);
+        assert!(diagnose(&[99], 1).is_err());
+        assert!(diagnose(&[3, 0, 3, 0, 99], 1).is_err());
+    }
+}
@@ -0,0 +1,56 @@
+//! Amplification Circuit, run from the topology files next to the input.
+use super::Solution;
+use crate::intcode::parse_program;
+use crate::intcode::topology::Topology;
+use failure::{err_msg, Fallible};
+
+const CHAIN: &str = include_str!("../../inputs/day7-part1.topology");
+const RING: &str = include_str!("../../inputs/day7-part2.topology");
+
+/// The best signal the amplifiers wired up by `text` can send to the thrusters.
+fn signal(data: &[i32], text: &str) -> Fallible<i32> {
+    let run = Topology::parse(text, |_| Ok(data.to_vec()))?.run()?;
+    run.outputs
+        .first()
+        .and_then(|(_, outputs)| outputs.last().cloned())
+        .ok_or_else(|| err_msg("no signal reached the thrusters"))
+}
+
+pub struct Puzzle;
+
+impl Solution for Puzzle {
+    type Input = Vec<i32>;
+    type Answer = i32;
+
+    fn parse(input: &str) -> Fallible<Vec<i32>> {
+        Ok(parse_program(input))
+    }
+
+    fn part1(data: &Vec<i32>) -> Fallible<i32> {
+        signal(data, CHAIN)
+    }
+
+    fn part2(data: &Vec<i32>) -> Fallible<i32> {
+        signal(data, RING)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_examples() {
+        let chain = Puzzle::parse(
+            "3,23,3,24,1002,24,10,24,1002,23,-1,23,101,5,23,23,1,24,23,23,4,23,99,0,0",
+        )
+        .unwrap();
+        assert_eq!(Puzzle::part1(&chain).unwrap(), 54321);
+        let ring = Puzzle::parse(
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,\
+             0,5",
+        )
+        .unwrap();
+        assert_eq!(Puzzle::part2(&ring).unwrap(), 139629729);
+    }
+}
@@ -0,0 +1,809 @@
+use self::devices::Device;
+use self::hooks::{Hook, Tracer};
+use crossbeam::channel::{Receiver, Sender};
+use failure::Fallible;
+use lazy_static::lazy_static;
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::{self, BufRead, BufReader, Write};
+use std::ops::Range;
+use std::path::Path;
+
+pub mod asynchronous;
+pub mod cfg;
+pub mod controller;
+pub mod devices;
+pub mod differential;
+pub mod fuzz;
+pub mod grid;
+pub mod hooks;
+pub mod legacy;
+pub mod maze;
+pub mod network;
+pub mod optimize;
+pub mod sweep;
+pub mod symbolic;
+pub mod topology;
+pub mod visual;
+
+lazy_static! {
+    static ref DEBUG: bool = std::env::var("DEBUG").is_ok();
+}
+
+pub trait Output {
+    fn output(&mut self, what: i32) -> Fallible<()>;
+    fn results(&self) -> Option<Vec<i32>>;
+}
+
+pub trait Input {
+    fn input(&mut self) -> Fallible<i32>;
+}
+
+pub trait Instruction {
+    fn execute(self, cpu: &mut IntcodeMachine) -> Fallible<()>;
+}
+
+pub struct MockInput {
+    current_input: usize,
+    inputs: Vec<i32>,
+}
+
+impl Input for Vec<i32> {
+    fn input(&mut self) -> Fallible<i32> {
+        self.pop().ok_or_else(|| MachineError::EOF.into())
+    }
+}
+
+impl Output for Vec<i32> {
+    fn output(&mut self, what: i32) -> Fallible<()> {
+        self.push(what);
+        Ok(())
+    }
+
+    fn results(&self) -> Option<Vec<i32>> {
+        Some(self.clone())
+    }
+}
+
+impl Input for MockInput {
+    fn input(&mut self) -> Fallible<i32> {
+        if let Some(x) = self.inputs.get(self.current_input) {
+            let result = Ok(*x);
+            self.current_input += 1;
+            result
+        } else {
+            Err(MachineError::EOF.into())
+        }
+    }
+}
+
+impl Input for io::Stdin {
+    fn input(&mut self) -> Fallible<i32> {
+        let mut buf = BufReader::new(self);
+        let mut s = String::new();
+        buf.read_line(&mut s)?;
+        Ok(s.trim().parse()?)
+    }
+}
+
+impl Output for io::Stdout {
+    fn output(&mut self, what: i32) -> Fallible<()> {
+        Ok(writeln!(self, "{}", what)?)
+    }
+
+    fn results(&self) -> Option<Vec<i32>> {
+        None
+    }
+}
+
+impl Output for Sender<i32> {
+    fn results(&self) -> Option<Vec<i32>> {
+        None
+    }
+    fn output(&mut self, what: i32) -> Fallible<()> {
+        self.send(what).map_err(Into::into)
+    }
+}
+
+impl Input for Receiver<i32> {
+    fn input(&mut self) -> Fallible<i32> {
+        self.recv().map_err(Into::into)
+    }
+}
+
+#[derive(Debug)]
+pub enum MachineError {
+    Halted,
+    OutOfBounds(usize, usize),
+    InvalidOpcode(i32),
+    EOF,
+    /// An `Add` or `Mul` whose result doesn't fit in an `i32`.
+    Overflow,
+    /// A device was attached over a cell that already has one.
+    DeviceOverlap(usize),
+}
+
+impl Display for MachineError {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        write!(w, "{:?}", self)
+    }
+}
+
+impl std::error::Error for MachineError {}
+
+/// Reads a comma separated Intcode program, dropping (and complaining about) anything that
+/// isn't a number.
+pub fn load_program<P: AsRef<Path>>(path: P) -> Fallible<Vec<i32>> {
+    Ok(parse_program(&std::fs::read_to_string(path)?))
+}
+
+/// [`load_program`] for a program that's already in memory.
+pub fn parse_program(text: &str) -> Vec<i32> {
+    let mut data: Vec<i32> = Vec::new();
+
+    for line in text.lines() {
+        for chunk in line.split(',') {
+            if let Ok(parsed) = chunk.trim().parse() {
+                data.push(parsed)
+            } else {
+                eprintln!("dropping chunk {}", chunk);
+            }
+        }
+    }
+
+    data
+}
+
+/// A machine minus its I/O, enough to fork a run and pick it back up later.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    ip: usize,
+    halted: bool,
+    data: Vec<i32>,
+}
+
+struct Mapping {
+    range: Range<usize>,
+    // reads go through `value_at(&self)`, and devices are allowed to change on a read
+    device: RefCell<Box<dyn Device>>,
+}
+
+pub struct IntcodeMachine {
+    ip: usize,
+    halted: bool,
+    data: Vec<i32>,
+    input: Box<dyn Input>,
+    output: Option<Box<dyn Output>>,
+    devices: Vec<Mapping>,
+    /// Where the instruction being executed started.
+    fetched: usize,
+    // same story as devices, `value_at` reports reads through `&self`
+    hooks: RefCell<Vec<Box<dyn Hook>>>,
+    /// Whether `hooks` has anything in it, so machines without any skip the borrow.
+    has_hooks: bool,
+}
+
+impl IntcodeMachine {
+    pub fn new(data: &[i32], input: Box<dyn Input>, output: Box<dyn Output>) -> IntcodeMachine {
+        let mut machine = IntcodeMachine {
+            ip: 0,
+            halted: false,
+            data: data.to_vec(),
+            input,
+            output: Some(output),
+            devices: Vec::new(),
+            fetched: 0,
+            hooks: RefCell::new(Vec::new()),
+            has_hooks: false,
+        };
+        if *DEBUG {
+            machine.add_hook(Box::new(Tracer::stdout()));
+        }
+        machine
+    }
+
+    /// Picks up a snapshotted run with fresh I/O.
+    pub fn restore(
+        snapshot: &Snapshot,
+        input: Box<dyn Input>,
+        output: Box<dyn Output>,
+    ) -> IntcodeMachine {
+        let mut machine = IntcodeMachine::new(&[], input, output);
+        machine.ip = snapshot.ip;
+        machine.halted = snapshot.halted;
+        machine.data = snapshot.data.clone();
+        machine
+    }
+
+    /// Maps `device` over `range`, so reads and writes through parameters go to it instead of
+    /// memory. The range may lie past the end of the program. Instruction fetches always read
+    /// plain memory, and snapshots don't carry devices along.
+    pub fn attach(&mut self, range: Range<usize>, device: Box<dyn Device>) -> Fallible<()> {
+        if let Some(m) = self
+            .devices
+            .iter()
+            .find(|m| m.range.start < range.end && range.start < m.range.end)
+        {
+            let cell = m.range.start.max(range.start);
+            return Err(MachineError::DeviceOverlap(cell).into());
+        }
+        self.devices.push(Mapping {
+            range,
+            device: RefCell::new(device),
+        });
+        Ok(())
+    }
+
+    /// Hooks run in the order they were added. Like devices, they aren't part of snapshots.
+    pub fn add_hook(&mut self, hook: Box<dyn Hook>) {
+        self.hooks.get_mut().push(hook);
+        self.has_hooks = true;
+    }
+
+    #[inline]
+    fn notify(&self, event: impl Fn(&mut dyn Hook)) {
+        if !self.has_hooks {
+            return;
+        }
+        for hook in self.hooks.borrow_mut().iter_mut() {
+            event(hook.as_mut());
+        }
+    }
+
+    fn device_at(&self, pos: usize) -> Option<&Mapping> {
+        self.devices.iter().find(|m| m.range.contains(&pos))
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            ip: self.ip,
+            halted: self.halted,
+            data: self.data.clone(),
+        }
+    }
+
+    pub fn take_output(mut self) -> Option<Box<dyn Output>> {
+        self.output.take()
+    }
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn memory(&self) -> &[i32] {
+        &self.data
+    }
+
+    pub fn set_cell(&mut self, pos: usize, val: i32) -> Fallible<()> {
+        if let Some(m) = self.device_at(pos) {
+            m.device.borrow_mut().write(pos - m.range.start, val);
+            Ok(())
+        } else if let Some(x) = self.data.get_mut(pos) {
+            let old = std::mem::replace(x, val);
+            self.notify(|h| h.on_write(pos, old, val));
+            Ok(())
+        } else {
+            Err(MachineError::OutOfBounds(pos, self.data.len()).into())
+        }
+    }
+
+    pub fn value_at(&self, pos: &Parameter) -> Fallible<i32> {
+        match *pos {
+            Parameter::Indirect(x) => {
+                if let Some(m) = self.device_at(x) {
+                    return Ok(m.device.borrow_mut().read(x - m.range.start));
+                }
+                let value = *self
+                    .data
+                    .get(x)
+                    .ok_or(MachineError::OutOfBounds(x, self.data.len()))?;
+                self.notify(|h| h.on_read(x, value));
+                Ok(value)
+            }
+            Parameter::Immediate(x) => Ok(x),
+        }
+    }
+
+    fn input(&mut self) -> Fallible<i32> {
+        let value = self.input.input()?;
+        self.notify(|h| h.on_input(value));
+        Ok(value)
+    }
+
+    fn output(&mut self, what: i32) -> Fallible<()> {
+        self.notify(|h| h.on_output(what));
+        if let Some(output) = self.output.as_mut() {
+            output.output(what)?
+        }
+        Ok(())
+    }
+
+    fn set_ip(&mut self, pos: usize) -> Fallible<()> {
+        if pos > self.data.len() {
+            Err(MachineError::OutOfBounds(pos, self.data.len()).into())
+        } else {
+            let from = self.fetched;
+            self.notify(|h| h.on_jump(from, pos));
+            self.ip = pos;
+            Ok(())
+        }
+    }
+
+    fn halt(&mut self) {
+        self.halted = true
+    }
+
+    pub fn unpack_op(&mut self) -> Fallible<Opcode> {
+        if self.halted {
+            return Err(MachineError::Halted.into());
+        }
+        let op = Opcode::decode(&self.data, self.ip)?;
+        let ip = self.ip;
+        self.fetched = ip;
+        self.notify(|h| h.on_fetch(ip, &op));
+        if op == Opcode::Halt {
+            self.notify(|h| h.on_halt(ip));
+        }
+        for m in self.devices.iter_mut() {
+            m.device.get_mut().tick();
+        }
+        if op != Opcode::Halt {
+            self.ip += op.len();
+        }
+        Ok(op)
+    }
+
+    pub fn run(&mut self) -> Fallible<()> {
+        loop {
+            match self.unpack_op()? {
+                Opcode::Halt => return Ok(()),
+                x => x.execute(self)?,
+            }
+        }
+    }
+
+    /// Like `run`, but gives up after `steps` instructions. `Ok(false)` means it gave up.
+    pub fn run_for(&mut self, steps: usize) -> Fallible<bool> {
+        for _ in 0..steps {
+            match self.unpack_op()? {
+                Opcode::Halt => return Ok(true),
+                x => x.execute(self)?,
+            }
+        }
+        Ok(false)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Parameter {
+    Immediate(i32),
+    Indirect(usize),
+}
+
+impl Parameter {
+    fn of_kind_and_value(kind: i32, value: i32) -> Fallible<Parameter> {
+        match kind {
+            0 => Ok(Parameter::Indirect(value as usize)),
+            1 => Ok(Parameter::Immediate(value)),
+            _ => Err(MachineError::InvalidOpcode(kind).into()),
+        }
+    }
+}
+
+impl Parameter {
+    fn mode(&self) -> i32 {
+        match self {
+            Parameter::Indirect(_) => 0,
+            Parameter::Immediate(_) => 1,
+        }
+    }
+
+    fn raw(&self) -> i32 {
+        match *self {
+            Parameter::Indirect(x) => x as i32,
+            Parameter::Immediate(x) => x,
+        }
+    }
+}
+
+impl Display for Parameter {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Parameter::Immediate(x) => write!(f, "{}", x),
+            Parameter::Indirect(x) => write!(f, "({})", x),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Opcode {
+    Add {
+        x: Parameter,
+        y: Parameter,
+        dest: usize,
+    },
+    Mul {
+        x: Parameter,
+        y: Parameter,
+        dest: usize,
+    },
+    Input {
+        x: usize,
+    },
+    Output {
+        x: Parameter,
+    },
+    JumpIfTrue {
+        x: Parameter,
+        dest: Parameter,
+    },
+    JumpIfFalse {
+        x: Parameter,
+        dest: Parameter,
+    },
+    LessThan {
+        x: Parameter,
+        y: Parameter,
+        dest: usize,
+    },
+    Equal {
+        x: Parameter,
+        y: Parameter,
+        dest: usize,
+    },
+    Halt,
+}
+
+impl Opcode {
+    /// Decodes the instruction starting at `ip` without touching any machine state, so the
+    /// same decoder serves both the interpreter and static analysis.
+    pub fn decode(data: &[i32], ip: usize) -> Fallible<Opcode> {
+        let op: i32 = *data
+            .get(ip)
+            .ok_or(MachineError::OutOfBounds(ip, data.len()))?;
+        let opcode = op % 100;
+        let flags = op / 100;
+        let arg = |n: usize| -> Fallible<i32> {
+            data.get(ip + n)
+                .cloned()
+                .ok_or_else(|| MachineError::OutOfBounds(ip + n, data.len()).into())
+        };
+
+        match opcode {
+            1 => Ok(Opcode::Add {
+                x: Parameter::of_kind_and_value(flags % 10, arg(1)?)?,
+                y: Parameter::of_kind_and_value(flags / 10, arg(2)?)?,
+                dest: arg(3)? as usize,
+            }),
+            2 => Ok(Opcode::Mul {
+                x: Parameter::of_kind_and_value(flags % 10, arg(1)?)?,
+                y: Parameter::of_kind_and_value(flags / 10, arg(2)?)?,
+                dest: arg(3)? as usize,
+            }),
+            3 => Ok(Opcode::Input {
+                x: arg(1)? as usize,
+            }),
+            4 => Ok(Opcode::Output {
+                x: Parameter::of_kind_and_value(flags % 10, arg(1)?)?,
+            }),
+            5 => Ok(Opcode::JumpIfTrue {
+                x: Parameter::of_kind_and_value(flags % 10, arg(1)?)?,
+                dest: Parameter::of_kind_and_value(flags / 10, arg(2)?)?,
+            }),
+            6 => Ok(Opcode::JumpIfFalse {
+                x: Parameter::of_kind_and_value(flags % 10, arg(1)?)?,
+                dest: Parameter::of_kind_and_value(flags / 10, arg(2)?)?,
+            }),
+            7 => Ok(Opcode::LessThan {
+                x: Parameter::of_kind_and_value(flags % 10, arg(1)?)?,
+                y: Parameter::of_kind_and_value(flags / 10, arg(2)?)?,
+                dest: arg(3)? as usize,
+            }),
+            8 => Ok(Opcode::Equal {
+                x: Parameter::of_kind_and_value(flags % 10, arg(1)?)?,
+                y: Parameter::of_kind_and_value(flags / 10, arg(2)?)?,
+                dest: arg(3)? as usize,
+            }),
+            99 => Ok(Opcode::Halt),
+            _ => Err(MachineError::InvalidOpcode(opcode).into()),
+        }
+    }
+
+    /// The cells [`decode`](Self::decode) would turn back into this instruction.
+    pub fn encode(&self) -> Vec<i32> {
+        let binary = |op: i32, x: &Parameter, y: &Parameter, dest: usize| {
+            vec![
+                op + 100 * x.mode() + 1000 * y.mode(),
+                x.raw(),
+                y.raw(),
+                dest as i32,
+            ]
+        };
+        match self {
+            Opcode::Add { x, y, dest } => binary(1, x, y, *dest),
+            Opcode::Mul { x, y, dest } => binary(2, x, y, *dest),
+            Opcode::Input { x } => vec![3, *x as i32],
+            Opcode::Output { x } => vec![4 + 100 * x.mode(), x.raw()],
+            Opcode::JumpIfTrue { x, dest } => {
+                vec![5 + 100 * x.mode() + 1000 * dest.mode(), x.raw(), dest.raw()]
+            }
+            Opcode::JumpIfFalse { x, dest } => {
+                vec![6 + 100 * x.mode() + 1000 * dest.mode(), x.raw(), dest.raw()]
+            }
+            Opcode::LessThan { x, y, dest } => binary(7, x, y, *dest),
+            Opcode::Equal { x, y, dest } => binary(8, x, y, *dest),
+            Opcode::Halt => vec![99],
+        }
+    }
+
+    /// Number of cells the instruction occupies, opcode included.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        match self {
+            Opcode::Add { .. } | Opcode::Mul { .. } => 4,
+            Opcode::LessThan { .. } | Opcode::Equal { .. } => 4,
+            Opcode::JumpIfTrue { .. } | Opcode::JumpIfFalse { .. } => 3,
+            Opcode::Input { .. } | Opcode::Output { .. } => 2,
+            Opcode::Halt => 1,
+        }
+    }
+}
+
+impl Display for Opcode {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        match self {
+            Opcode::Add { x, y, dest } => write!(w, "add {} + {} => {}", x, y, dest),
+            Opcode::Mul { x, y, dest } => write!(w, "mul {} * {} => {}", x, y, dest),
+            Opcode::Input { x } => write!(w, "input -> {}", x),
+            Opcode::Output { x } => write!(w, "{} -> output", x),
+            Opcode::JumpIfFalse { x, dest } => write!(w, "jmp-false {} -> {}", x, dest),
+            Opcode::JumpIfTrue { x, dest } => write!(w, "jmp-true {} -> {}", x, dest),
+            Opcode::LessThan { x, y, dest } => write!(w, "lessthan {} < {} => {}", x, y, dest),
+            Opcode::Equal { x, y, dest } => write!(w, "equal {} == {} => {}", x, y, dest),
+            Opcode::Halt => write!(w, "halt"),
+        }
+    }
+}
+
+impl Instruction for Opcode {
+    fn execute(self, cpu: &mut IntcodeMachine) -> Fallible<()> {
+        match self {
+            Opcode::Add { x, y, dest } => {
+                let sum = cpu.value_at(&x)?.checked_add(cpu.value_at(&y)?);
+                cpu.set_cell(dest, sum.ok_or(MachineError::Overflow)?)?
+            }
+            Opcode::Mul { x, y, dest } => {
+                let product = cpu.value_at(&x)?.checked_mul(cpu.value_at(&y)?);
+                cpu.set_cell(dest, product.ok_or(MachineError::Overflow)?)?
+            }
+            Opcode::Input { x } => {
+                let value = cpu.input()?;
+                cpu.set_cell(x, value)?;
+            }
+            Opcode::Output { x } => {
+                let value = cpu.value_at(&x)?;
+                cpu.output(value)?;
+            }
+            Opcode::JumpIfTrue { x, dest } => {
+                if cpu.value_at(&x)? != 0 {
+                    cpu.set_ip(cpu.value_at(&dest)? as usize)?;
+                }
+            }
+            Opcode::JumpIfFalse { x, dest } => {
+                if cpu.value_at(&x)? == 0 {
+                    cpu.set_ip(cpu.value_at(&dest)? as usize)?;
+                }
+            }
+            Opcode::LessThan { x, y, dest } => {
+                if cpu.value_at(&x)? < cpu.value_at(&y)? {
+                    cpu.set_cell(dest, 1)?;
+                } else {
+                    cpu.set_cell(dest, 0)?;
+                }
+            }
+            Opcode::Equal { x, y, dest } => {
+                if cpu.value_at(&x)? == cpu.value_at(&y)? {
+                    cpu.set_cell(dest, 1)?;
+                } else {
+                    cpu.set_cell(dest, 0)?;
+                }
+            }
+            Opcode::Halt => cpu.halt(),
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_single_add() {
+        let data = &[1, 0, 0, 0, 99];
+        let mut machine = IntcodeMachine::new(data, Box::new(Vec::new()), Box::new(Vec::new()));
+        let result = machine.unpack_op().unwrap();
+        let expected = Opcode::Add {
+            x: Parameter::Indirect(0),
+            y: Parameter::Indirect(0),
+            dest: 0,
+        };
+        assert_eq!(result, expected);
+        assert_eq!(machine.unpack_op().unwrap(), Opcode::Halt);
+    }
+
+    #[test]
+    fn test_read_single_mul() {
+        let data = &[2, 0, 0, 0, 99];
+        let mut machine = IntcodeMachine::new(data, Box::new(Vec::new()), Box::new(Vec::new()));
+        let result = machine.unpack_op().unwrap();
+        let expected = Opcode::Mul {
+            x: Parameter::Indirect(0),
+            y: Parameter::Indirect(0),
+            dest: 0,
+        };
+        assert_eq!(result, expected);
+        assert_eq!(machine.unpack_op().unwrap(), Opcode::Halt);
+    }
+
+    #[test]
+    fn test_single_add() {
+        let data = &[1, 5, 2, 3, 99, 0];
+        let mut machine = IntcodeMachine::new(data, Box::new(Vec::new()), Box::new(Vec::new()));
+        machine.run().unwrap();
+        assert_eq!(machine.value_at(&Parameter::Indirect(3)).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_single_mul() {
+        let data = &[2, 0, 0, 3, 99];
+        let mut machine = IntcodeMachine::new(data, Box::new(Vec::new()), Box::new(Vec::new()));
+        machine.run().unwrap();
+        assert_eq!(machine.value_at(&Parameter::Indirect(3)).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_io() {
+        let ins: Box<Vec<i32>> = Box::new(vec![99]);
+        let outs: Box<Vec<i32>> = Box::default();
+        let data = vec![3, 3, 104, 0, 99];
+        let mut machine = IntcodeMachine::new(&data, ins, outs);
+        machine.run().unwrap();
+        println!("{:?}", &machine.data.clone());
+        let output = machine.take_output().unwrap();
+        assert_eq!(output.results(), Some(vec![99]));
+    }
+
+    #[test]
+    fn test_jmp_if_false() {
+        let ins: Box<Vec<i32>> = Box::default();
+        let outs: Box<Vec<i32>> = Box::default();
+        let data = vec![
+            1106, 0, 6, // jump to 6 if 0 is false
+            104, 69, 99, // trap! 69 is bad number
+            104, 420, 99, // print imm(420)
+        ];
+        let mut machine = IntcodeMachine::new(&data, ins, outs);
+        machine.run().unwrap();
+        println!("{:?}", &machine.data.clone());
+        let output = machine.take_output().unwrap();
+        assert_eq!(output.results(), Some(vec![420]));
+    }
+
+    #[test]
+    fn test_jmp_if_true() {
+        let ins: Box<Vec<i32>> = Box::default();
+        let outs: Box<Vec<i32>> = Box::default();
+        let data = vec![
+            105, 0, 6, // jump to 6 if 0 is true
+            104, 69, 99, // trap! 69 is bad number
+            104, 420, 99, // print imm(420)
+        ];
+        let mut machine = IntcodeMachine::new(&data, ins, outs);
+        machine.run().unwrap();
+        let output = machine.take_output().unwrap();
+        assert_eq!(output.results(), Some(vec![69]));
+    }
+
+    #[test]
+    fn test_equal() {
+        for (i, expected) in [(7, 0), (8, 1), (9, 0)] {
+            let ins: Box<Vec<i32>> = Box::new(vec![i]);
+            let outs: Box<Vec<i32>> = Box::default();
+            let data = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+            let mut machine = IntcodeMachine::new(&data, ins, outs);
+            machine.run().unwrap();
+            let output = machine.take_output().unwrap();
+            assert_eq!(output.results(), Some(vec![expected]));
+        }
+    }
+
+    #[test]
+    fn test_equal_imm() {
+        for (i, expected) in [(7, 0), (8, 1), (9, 0)] {
+            let ins: Box<Vec<i32>> = Box::new(vec![i]);
+            let outs: Box<Vec<i32>> = Box::default();
+            let data = vec![3, 3, 1108, -1, 8, 3, 4, 3, 99];
+            let mut machine = IntcodeMachine::new(&data, ins, outs);
+            machine.run().unwrap();
+            let output = machine.take_output().unwrap();
+            assert_eq!(output.results(), Some(vec![expected]));
+        }
+    }
+
+    #[test]
+    fn test_less() {
+        for (i, expected) in [(7, 1), (8, 0), (9, 0)] {
+            let ins: Box<Vec<i32>> = Box::new(vec![i]);
+            let outs: Box<Vec<i32>> = Box::default();
+            let data = vec![3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8];
+            let mut machine = IntcodeMachine::new(&data, ins, outs);
+            machine.run().unwrap();
+            let output = machine.take_output().unwrap();
+            assert_eq!(output.results(), Some(vec![expected]));
+        }
+    }
+
+    #[test]
+    fn test_less_imm() {
+        for (i, expected) in [(7, 1), (8, 0), (9, 0)] {
+            let ins: Box<Vec<i32>> = Box::new(vec![i]);
+            let outs: Box<Vec<i32>> = Box::default();
+            let data = vec![3, 3, 1107, -1, 8, 3, 4, 3, 99];
+            let mut machine = IntcodeMachine::new(&data, ins, outs);
+            machine.run().unwrap();
+            let output = machine.take_output().unwrap();
+            assert_eq!(output.results(), Some(vec![expected]));
+        }
+    }
+
+    #[test]
+    fn test_large_program() {
+        for (i, expected) in [(7, 999), (8, 1000), (9, 1001)] {
+            let ins: Box<Vec<i32>> = Box::new(vec![i]);
+            let outs: Box<Vec<i32>> = Box::default();
+            let data = vec![
+                3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36,
+                98, 0, 0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000,
+                1, 20, 4, 20, 1105, 1, 46, 98, 99,
+            ];
+            let mut machine = IntcodeMachine::new(&data, ins, outs);
+            machine.run().unwrap();
+            let output = machine.take_output().unwrap();
+            assert_eq!(output.results(), Some(vec![expected]));
+        }
+    }
+
+    #[test]
+    fn test_snapshot_forks() {
+        // out(in + in)
+        let data = &[3, 11, 3, 12, 1, 11, 12, 11, 4, 11, 99, 0, 0];
+        let mut machine = IntcodeMachine::new(data, Box::new(vec![5]), Box::new(Vec::new()));
+        machine.unpack_op().unwrap().execute(&mut machine).unwrap();
+        let snapshot = machine.snapshot();
+        for (second, expected) in [(1, 6), (-5, 0)] {
+            let mut fork =
+                IntcodeMachine::restore(&snapshot, Box::new(vec![second]), Box::new(Vec::new()));
+            fork.run().unwrap();
+            let output = fork.take_output().unwrap();
+            assert_eq!(output.results(), Some(vec![expected]));
+        }
+        assert_eq!(machine.snapshot(), snapshot);
+    }
+
+    #[test]
+    fn test_encode_roundtrip() {
+        let data = &[
+            1, 5, 6, 7, 1102, -3, 4, 0, 3, 9, 104, 12, 1005, 3, 0, 106, 0, 7, 1007, 1, 2, 3, 108,
+            4, 5, 6, 99,
+        ];
+        let mut ip = 0;
+        let mut encoded = Vec::new();
+        while ip < data.len() {
+            let op = Opcode::decode(data, ip).unwrap();
+            encoded.extend(op.encode());
+            ip += op.len();
+        }
+        assert_eq!(&encoded[..], &data[..]);
+    }
+}
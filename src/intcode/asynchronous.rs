@@ -0,0 +1,455 @@
+//! Intcode machines that await their I/O, so many of them can share one thread.
+//!
+//! [`AsyncIntcodeMachine`] wraps the ordinary interpreter and only takes over `Input` and
+//! `Output`, which become await points. [`Executor`] is a small single-threaded executor to run
+//! a bunch of them, with a seeded scheduler to shake out ordering bugs reproducibly.
+use super::devices::Rng;
+use super::{Instruction, IntcodeMachine, MachineError, Opcode};
+use failure::Fallible;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+/// Instructions a machine runs before giving other tasks a turn.
+const SLICE: usize = 1000;
+
+pub trait AsyncInput {
+    fn poll_input(&mut self, cx: &mut Context) -> Poll<Fallible<i32>>;
+}
+
+pub trait AsyncOutput {
+    fn poll_output(&mut self, cx: &mut Context, what: i32) -> Poll<Fallible<()>>;
+    fn results(&self) -> Option<Vec<i32>>;
+}
+
+impl AsyncInput for Vec<i32> {
+    fn poll_input(&mut self, _cx: &mut Context) -> Poll<Fallible<i32>> {
+        Poll::Ready(self.pop().ok_or_else(|| MachineError::EOF.into()))
+    }
+}
+
+impl AsyncOutput for Vec<i32> {
+    fn poll_output(&mut self, _cx: &mut Context, what: i32) -> Poll<Fallible<()>> {
+        self.push(what);
+        Poll::Ready(Ok(()))
+    }
+
+    fn results(&self) -> Option<Vec<i32>> {
+        Some(self.clone())
+    }
+}
+
+struct Shared {
+    queue: VecDeque<i32>,
+    waker: Option<Waker>,
+    senders: usize,
+}
+
+/// Unbounded single-threaded channel. Reading from it once every sender is gone is `EOF`.
+pub fn channel() -> (AsyncSender, AsyncReceiver) {
+    let shared = Rc::new(RefCell::new(Shared {
+        queue: VecDeque::new(),
+        waker: None,
+        senders: 1,
+    }));
+    (AsyncSender(shared.clone()), AsyncReceiver(shared))
+}
+
+pub struct AsyncSender(Rc<RefCell<Shared>>);
+
+pub struct AsyncReceiver(Rc<RefCell<Shared>>);
+
+impl AsyncSender {
+    pub fn send(&self, what: i32) {
+        let mut shared = self.0.borrow_mut();
+        shared.queue.push_back(what);
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Clone for AsyncSender {
+    fn clone(&self) -> AsyncSender {
+        self.0.borrow_mut().senders += 1;
+        AsyncSender(self.0.clone())
+    }
+}
+
+impl Drop for AsyncSender {
+    fn drop(&mut self) {
+        let mut shared = self.0.borrow_mut();
+        shared.senders -= 1;
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl AsyncReceiver {
+    pub fn try_recv(&self) -> Option<i32> {
+        self.0.borrow_mut().queue.pop_front()
+    }
+
+    pub async fn recv(&mut self) -> Fallible<i32> {
+        poll_fn(|cx| self.poll_input(cx)).await
+    }
+
+    /// Everything queued up so far.
+    pub fn drain(&self) -> Vec<i32> {
+        self.0.borrow_mut().queue.drain(..).collect()
+    }
+}
+
+impl AsyncInput for AsyncReceiver {
+    fn poll_input(&mut self, cx: &mut Context) -> Poll<Fallible<i32>> {
+        let mut shared = self.0.borrow_mut();
+        if let Some(x) = shared.queue.pop_front() {
+            Poll::Ready(Ok(x))
+        } else if shared.senders == 0 {
+            Poll::Ready(Err(MachineError::EOF.into()))
+        } else {
+            shared.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl AsyncOutput for AsyncSender {
+    fn poll_output(&mut self, _cx: &mut Context, what: i32) -> Poll<Fallible<()>> {
+        self.send(what);
+        Poll::Ready(Ok(()))
+    }
+
+    fn results(&self) -> Option<Vec<i32>> {
+        None
+    }
+}
+
+/// Gives the executor a chance to run something else.
+pub async fn yield_now() {
+    let mut yielded = false;
+    poll_fn(|cx| {
+        if yielded {
+            Poll::Ready(())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+pub struct AsyncIntcodeMachine {
+    cpu: IntcodeMachine,
+    input: Box<dyn AsyncInput>,
+    output: Option<Box<dyn AsyncOutput>>,
+}
+
+impl AsyncIntcodeMachine {
+    pub fn new(
+        data: &[i32],
+        input: Box<dyn AsyncInput>,
+        output: Box<dyn AsyncOutput>,
+    ) -> AsyncIntcodeMachine {
+        AsyncIntcodeMachine {
+            // I/O never reaches the inner machine, we intercept it in run()
+            cpu: IntcodeMachine::new(data, Box::new(Vec::new()), Box::new(Vec::new())),
+            input,
+            output: Some(output),
+        }
+    }
+
+    pub fn take_output(mut self) -> Option<Box<dyn AsyncOutput>> {
+        self.output.take()
+    }
+
+    pub fn memory(&self) -> &[i32] {
+        self.cpu.memory()
+    }
+
+    pub async fn run(&mut self) -> Fallible<()> {
+        let mut steps = 0;
+        loop {
+            steps += 1;
+            if steps % SLICE == 0 {
+                yield_now().await;
+            }
+            match self.cpu.unpack_op()? {
+                Opcode::Halt => return Ok(()),
+                Opcode::Input { x } => {
+                    let input = &mut self.input;
+                    let value = poll_fn(|cx| input.poll_input(cx)).await?;
+                    self.cpu.set_cell(x, value)?;
+                }
+                Opcode::Output { x } => {
+                    let what = self.cpu.value_at(&x)?;
+                    if let Some(output) = self.output.as_mut() {
+                        poll_fn(|cx| output.poll_output(cx, what)).await?;
+                    }
+                }
+                op => op.execute(&mut self.cpu)?,
+            }
+        }
+    }
+}
+
+/// The error for a task that was still waiting when nothing was left to run.
+#[derive(Debug)]
+pub struct Deadlock;
+
+impl Display for Deadlock {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        write!(w, "task was blocked forever")
+    }
+}
+
+impl std::error::Error for Deadlock {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Schedule {
+    /// Ready tasks run in the order they were woken.
+    Fifo,
+    /// Ready tasks run in a pseudo-random order fixed by the seed.
+    Seeded(u64),
+}
+
+struct TaskWaker {
+    id: usize,
+    ready: Arc<Mutex<VecDeque<usize>>>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.ready.lock().unwrap().push_back(self.id);
+    }
+}
+
+type Task = Pin<Box<dyn Future<Output = Fallible<()>>>>;
+
+pub struct Executor {
+    tasks: Vec<Option<Task>>,
+    results: Vec<Option<Fallible<()>>>,
+    ready: Arc<Mutex<VecDeque<usize>>>,
+    /// Picks the next ready task, unless they run first come first served.
+    rng: Option<Rng>,
+}
+
+impl Default for Executor {
+    fn default() -> Executor {
+        Executor::new(Schedule::Fifo)
+    }
+}
+
+impl Executor {
+    pub fn new(schedule: Schedule) -> Executor {
+        Executor {
+            tasks: Vec::new(),
+            results: Vec::new(),
+            ready: Arc::new(Mutex::new(VecDeque::new())),
+            rng: match schedule {
+                Schedule::Fifo => None,
+                Schedule::Seeded(seed) => Some(Rng::new(seed)),
+            },
+        }
+    }
+
+    /// Queues a task, returning its index into the results of [`run`](Self::run).
+    pub fn spawn<F: Future<Output = Fallible<()>> + 'static>(&mut self, task: F) -> usize {
+        let id = self.tasks.len();
+        self.tasks.push(Some(Box::pin(task)));
+        self.results.push(None);
+        self.ready.lock().unwrap().push_back(id);
+        id
+    }
+
+    fn next_ready(&mut self) -> Option<usize> {
+        let mut ready = self.ready.lock().unwrap();
+        match &mut self.rng {
+            None => ready.pop_front(),
+            Some(_) if ready.is_empty() => None,
+            Some(rng) => {
+                let pick = rng.below(ready.len());
+                ready.remove(pick)
+            }
+        }
+    }
+
+    /// Polls tasks until none are ready. Each task's result comes back at its spawn index;
+    /// anything that never finished gets a [`Deadlock`] error.
+    pub fn run(&mut self) -> Vec<Fallible<()>> {
+        while let Some(id) = self.next_ready() {
+            let task = match self.tasks.get_mut(id) {
+                Some(Some(task)) => task,
+                _ => continue,
+            };
+            let waker = Waker::from(Arc::new(TaskWaker {
+                id,
+                ready: self.ready.clone(),
+            }));
+            if let Poll::Ready(result) = task.as_mut().poll(&mut Context::from_waker(&waker)) {
+                self.tasks[id] = None;
+                self.results[id] = Some(result);
+            }
+        }
+        // dropping a task can wake it, so only forget the wakes afterwards
+        self.tasks.clear();
+        self.ready.lock().unwrap().clear();
+        self.results
+            .drain(..)
+            .map(|r| r.unwrap_or_else(|| Err(Deadlock.into())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn amplifiers(program: &[i32], phases: &[i32], schedule: Schedule) -> i32 {
+        let mut executor = Executor::new(schedule);
+        let (first, mut rx) = channel();
+        first.send(phases[0]);
+        first.send(0);
+        for phase in phases[1..].iter() {
+            let (tx, next) = channel();
+            tx.send(*phase);
+            let mut machine = AsyncIntcodeMachine::new(program, Box::new(rx), Box::new(tx));
+            executor.spawn(async move { machine.run().await });
+            rx = next;
+        }
+        let (tx, mut last) = channel();
+        let mut machine = AsyncIntcodeMachine::new(program, Box::new(rx), Box::new(tx));
+        executor.spawn(async move { machine.run().await });
+
+        // feed the last amplifier back into the first, remembering what went past
+        let signal = Rc::new(Cell::new(0));
+        let seen = signal.clone();
+        executor.spawn(async move {
+            while let Ok(x) = last.recv().await {
+                seen.set(x);
+                first.send(x);
+            }
+            Ok(())
+        });
+
+        for result in executor.run() {
+            result.unwrap();
+        }
+        signal.get()
+    }
+
+    #[test]
+    fn test_amplifier_chain() {
+        let program = &[
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+        assert_eq!(amplifiers(program, &[4, 3, 2, 1, 0], Schedule::Fifo), 43210);
+    }
+
+    #[test]
+    fn test_feedback_loop_any_schedule() {
+        let program = &[
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        for schedule in [Schedule::Fifo, Schedule::Seeded(1), Schedule::Seeded(1234)] {
+            assert_eq!(amplifiers(program, &[9, 8, 7, 6, 5], schedule), 139629729);
+        }
+    }
+
+    #[test]
+    fn test_blocked_task_is_deadlocked() {
+        let (sender, receiver) = channel();
+        let mut machine =
+            AsyncIntcodeMachine::new(&[3, 0, 99], Box::new(receiver), Box::new(Vec::new()));
+        let mut executor = Executor::default();
+        executor.spawn(async move { machine.run().await });
+        let results = executor.run();
+        assert!(results[0]
+            .as_ref()
+            .unwrap_err()
+            .downcast_ref::<Deadlock>()
+            .is_some());
+        drop(sender);
+    }
+
+    #[test]
+    fn test_closed_channel_is_eof() {
+        let (sender, receiver) = channel();
+        drop(sender);
+        let mut machine =
+            AsyncIntcodeMachine::new(&[3, 0, 99], Box::new(receiver), Box::new(Vec::new()));
+        let mut executor = Executor::default();
+        executor.spawn(async move { machine.run().await });
+        assert!(executor.run()[0].is_err());
+    }
+
+    /// Never finishes, and wakes itself when dropped.
+    struct WakeOnDrop(Option<Waker>);
+
+    impl Future for WakeOnDrop {
+        type Output = Fallible<()>;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Fallible<()>> {
+            self.0 = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    impl Drop for WakeOnDrop {
+        fn drop(&mut self) {
+            if let Some(waker) = self.0.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    #[test]
+    fn test_reuse_after_wake_on_drop() {
+        let mut executor = Executor::default();
+        executor.spawn(async { Ok(()) });
+        executor.spawn(WakeOnDrop(None));
+        assert_eq!(executor.run().len(), 2);
+        // the dropped task woke id 1, which no longer exists
+        executor.spawn(async { Ok(()) });
+        let results = executor.run();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_ok());
+    }
+
+    #[test]
+    fn test_seed_zero_still_shuffles() {
+        let order = |schedule| {
+            let order = Rc::new(RefCell::new(Vec::new()));
+            let mut executor = Executor::new(schedule);
+            for i in 0..8 {
+                let order = order.clone();
+                executor.spawn(async move {
+                    order.borrow_mut().push(i);
+                    Ok(())
+                });
+            }
+            executor.run();
+            let order = order.borrow().clone();
+            order
+        };
+        let fifo: Vec<i32> = (0..8).collect();
+        assert_eq!(order(Schedule::Fifo), fifo);
+        let mut shuffled = order(Schedule::Seeded(0));
+        assert_ne!(shuffled, fifo);
+        shuffled.sort_unstable();
+        assert_eq!(shuffled, fifo);
+    }
+}
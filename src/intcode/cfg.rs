@@ -0,0 +1,284 @@
+//! Static control flow recovery for Intcode images.
+//!
+//! Decoding starts at address 0 and follows every edge we can see without running the program:
+//! fallthrough, and `JumpIfTrue`/`JumpIfFalse` with an immediate destination. Jumps through
+//! memory end up pointing at [`Target::Unknown`]. Self-modifying code isn't modelled, so treat
+//! the result as a map of what the image looks like at load time.
+use super::{Opcode, Parameter};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Target {
+    Block(usize),
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    Fallthrough,
+    Taken,
+    NotTaken,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub kind: EdgeKind,
+    pub target: Target,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    pub start: usize,
+    /// One past the last cell of the last instruction.
+    pub end: usize,
+    pub instructions: Vec<(usize, Opcode)>,
+    pub successors: Vec<Edge>,
+    /// Set when decoding fell over at `end`, e.g. we walked into data.
+    pub error: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ControlFlowGraph {
+    pub blocks: BTreeMap<usize, BasicBlock>,
+    len: usize,
+    covered: BTreeSet<usize>,
+}
+
+fn successors(addr: usize, op: &Opcode) -> Vec<Edge> {
+    let next = addr + op.len();
+    let (x, dest, jump_if) = match op {
+        Opcode::Halt => return vec![],
+        Opcode::JumpIfTrue { x, dest } => (x, dest, true),
+        Opcode::JumpIfFalse { x, dest } => (x, dest, false),
+        _ => {
+            return vec![Edge {
+                kind: EdgeKind::Fallthrough,
+                target: Target::Block(next),
+            }]
+        }
+    };
+    let taken = Edge {
+        kind: EdgeKind::Taken,
+        target: match dest {
+            Parameter::Immediate(d) => Target::Block(*d as usize),
+            Parameter::Indirect(_) => Target::Unknown,
+        },
+    };
+    let not_taken = Edge {
+        kind: EdgeKind::NotTaken,
+        target: Target::Block(next),
+    };
+    match x {
+        // an immediate condition is decided at load time, so only one edge is real
+        Parameter::Immediate(v) if (*v != 0) == jump_if => vec![taken],
+        Parameter::Immediate(_) => vec![not_taken],
+        Parameter::Indirect(_) => vec![taken, not_taken],
+    }
+}
+
+fn is_jump(op: &Opcode) -> bool {
+    matches!(
+        op,
+        Opcode::JumpIfTrue { .. } | Opcode::JumpIfFalse { .. } | Opcode::Halt
+    )
+}
+
+impl ControlFlowGraph {
+    pub fn build(data: &[i32]) -> ControlFlowGraph {
+        let mut decoded: BTreeMap<usize, Result<Opcode, String>> = BTreeMap::new();
+        let mut leaders: BTreeSet<usize> = BTreeSet::new();
+        let mut work = vec![0];
+        leaders.insert(0);
+
+        while let Some(addr) = work.pop() {
+            if decoded.contains_key(&addr) {
+                continue;
+            }
+            match Opcode::decode(data, addr) {
+                Ok(op) => {
+                    for edge in successors(addr, &op) {
+                        if let Target::Block(t) = edge.target {
+                            if is_jump(&op) {
+                                leaders.insert(t);
+                            }
+                            work.push(t);
+                        }
+                    }
+                    decoded.insert(addr, Ok(op));
+                }
+                Err(e) => {
+                    decoded.insert(addr, Err(e.to_string()));
+                }
+            }
+        }
+
+        let mut covered = BTreeSet::new();
+        for (addr, op) in decoded.iter() {
+            let len = op.as_ref().map(Opcode::len).unwrap_or(1);
+            covered.extend(*addr..(*addr + len).min(data.len()));
+        }
+
+        let mut blocks = BTreeMap::new();
+        for leader in leaders.iter() {
+            let mut block = BasicBlock {
+                start: *leader,
+                end: *leader,
+                instructions: Vec::new(),
+                successors: Vec::new(),
+                error: None,
+            };
+            let mut addr = *leader;
+            loop {
+                match decoded.get(&addr) {
+                    Some(Ok(op)) => {
+                        block.instructions.push((addr, *op));
+                        block.end = addr + op.len();
+                        let next = successors(addr, op);
+                        if is_jump(op) || leaders.contains(&block.end) {
+                            block.successors = next;
+                            break;
+                        }
+                        addr = block.end;
+                    }
+                    Some(Err(e)) => {
+                        block.error = Some(e.clone());
+                        break;
+                    }
+                    None => unreachable!("every successor gets decoded"),
+                }
+            }
+            blocks.insert(*leader, block);
+        }
+
+        ControlFlowGraph {
+            blocks,
+            len: data.len(),
+            covered,
+        }
+    }
+
+    /// Addresses of jumps whose destination is read from memory.
+    pub fn indirect_jumps(&self) -> Vec<usize> {
+        self.blocks
+            .values()
+            .filter(|b| b.successors.iter().any(|e| e.target == Target::Unknown))
+            .filter_map(|b| b.instructions.last().map(|(addr, _)| *addr))
+            .collect()
+    }
+
+    /// Ranges of the image no decoded instruction touches. Only a hint while
+    /// [`indirect_jumps`](Self::indirect_jumps) is non-empty.
+    pub fn unreachable(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for addr in (0..self.len).filter(|a| !self.covered.contains(a)) {
+            match ranges.last_mut() {
+                Some(r) if r.end == addr => r.end += 1,
+                _ => ranges.push(addr..addr + 1),
+            }
+        }
+        ranges
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph intcode {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            for (addr, op) in block.instructions.iter() {
+                write!(label, "{:>4}: {}\\l", addr, op).unwrap();
+            }
+            if let Some(e) = &block.error {
+                write!(label, "{:>4}: <{}>\\l", block.end, e.replace('"', "'")).unwrap();
+            }
+            writeln!(dot, "    b{} [label=\"{}\"];", block.start, label).unwrap();
+        }
+        let mut unknown = false;
+        for block in self.blocks.values() {
+            for edge in block.successors.iter() {
+                let to = match edge.target {
+                    Target::Block(t) => format!("b{}", t),
+                    Target::Unknown => {
+                        unknown = true;
+                        "unknown".to_string()
+                    }
+                };
+                let attrs = match edge.kind {
+                    EdgeKind::Fallthrough => "",
+                    EdgeKind::Taken => " [label=\"taken\"]",
+                    EdgeKind::NotTaken => " [label=\"not taken\", style=dashed]",
+                };
+                writeln!(dot, "    b{} -> {}{};", block.start, to, attrs).unwrap();
+            }
+        }
+        if unknown {
+            writeln!(dot, "    unknown [shape=diamond, label=\"?\"];").unwrap();
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unconditional_jump() {
+        let data = &[1105, 1, 4, 99, 104, 7, 99];
+        let cfg = ControlFlowGraph::build(data);
+        assert_eq!(cfg.blocks.keys().cloned().collect::<Vec<_>>(), vec![0, 4]);
+        assert_eq!(
+            cfg.blocks[&0].successors,
+            vec![Edge {
+                kind: EdgeKind::Taken,
+                target: Target::Block(4)
+            }]
+        );
+        assert_eq!(cfg.blocks[&4].end, 7);
+        assert_eq!(cfg.unreachable(), vec![3..4]);
+    }
+
+    #[test]
+    fn test_conditional_jump_splits_blocks() {
+        let data = &[3, 9, 1005, 9, 7, 104, 0, 99, 0, 0];
+        let cfg = ControlFlowGraph::build(data);
+        assert_eq!(
+            cfg.blocks.keys().cloned().collect::<Vec<_>>(),
+            vec![0, 5, 7]
+        );
+        assert_eq!(cfg.blocks[&0].instructions.len(), 2);
+        assert_eq!(
+            cfg.blocks[&5].successors,
+            vec![Edge {
+                kind: EdgeKind::Fallthrough,
+                target: Target::Block(7)
+            }]
+        );
+        assert_eq!(cfg.unreachable(), vec![8..10]);
+        assert!(cfg.indirect_jumps().is_empty());
+    }
+
+    #[test]
+    fn test_indirect_jump_is_unknown() {
+        let data = &[6, 4, 5, 99, 0, 3];
+        let cfg = ControlFlowGraph::build(data);
+        assert_eq!(cfg.indirect_jumps(), vec![0]);
+        let dot = cfg.to_dot();
+        assert!(dot.contains("b0 -> unknown [label=\"taken\"];"));
+        assert!(dot.contains("b0 -> b3 [label=\"not taken\", style=dashed];"));
+        assert_eq!(cfg.unreachable(), vec![4..6]);
+    }
+
+    #[test]
+    fn test_walking_into_data() {
+        let data = &[1, 0, 0, 0, 42];
+        let cfg = ControlFlowGraph::build(data);
+        assert_eq!(cfg.blocks.len(), 1);
+        assert_eq!(cfg.blocks[&0].end, 4);
+        assert!(cfg.blocks[&0].error.is_some());
+        assert!(cfg.unreachable().is_empty());
+    }
+}
@@ -0,0 +1,232 @@
+//! Driving an Intcode program from Rust, one decision at a time.
+//!
+//! A [`Session`] owns the machine and runs it until it asks for input. The [`Controller`] then
+//! sees everything the program output since the last input and picks the next one. Every value
+//! going either way lands in the session's transcript.
+use super::{Instruction, IntcodeMachine, Opcode};
+use failure::Fallible;
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decision {
+    Input(i32),
+    /// Pause the session. A later `run` asks again.
+    Stop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exchange {
+    Input(i32),
+    Output(i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ending {
+    Halted,
+    Stopped,
+}
+
+pub trait Controller {
+    /// Called when the program wants input, with everything it output since the last input.
+    fn decide(&mut self, outputs: &[i32]) -> Fallible<Decision>;
+
+    /// Called once the program halts, with whatever it output after the last input.
+    fn finish(&mut self, _outputs: &[i32]) -> Fallible<()> {
+        Ok(())
+    }
+}
+
+impl<F: FnMut(&[i32]) -> Fallible<Decision>> Controller for F {
+    fn decide(&mut self, outputs: &[i32]) -> Fallible<Decision> {
+        self(outputs)
+    }
+}
+
+/// Feeds a fixed list of inputs in order and collects every output.
+#[derive(Debug, Default)]
+pub struct Scripted {
+    inputs: VecDeque<i32>,
+    pub outputs: Vec<i32>,
+}
+
+impl Scripted {
+    pub fn new(inputs: Vec<i32>) -> Scripted {
+        Scripted {
+            inputs: inputs.into(),
+            outputs: Vec::new(),
+        }
+    }
+}
+
+impl Controller for Scripted {
+    fn decide(&mut self, outputs: &[i32]) -> Fallible<Decision> {
+        self.outputs.extend_from_slice(outputs);
+        Ok(self
+            .inputs
+            .pop_front()
+            .map(Decision::Input)
+            .unwrap_or(Decision::Stop))
+    }
+
+    fn finish(&mut self, outputs: &[i32]) -> Fallible<()> {
+        self.outputs.extend_from_slice(outputs);
+        Ok(())
+    }
+}
+
+pub struct Session<C: Controller> {
+    machine: IntcodeMachine,
+    controller: C,
+    transcript: Vec<Exchange>,
+    /// Outputs the controller hasn't seen yet.
+    unseen: Vec<i32>,
+    /// An `Input` whose decision was `Stop`, to be retried on the next run.
+    waiting: Option<usize>,
+}
+
+impl<C: Controller> Session<C> {
+    pub fn new(program: &[i32], controller: C) -> Session<C> {
+        Session {
+            // I/O never reaches the machine, the session intercepts it
+            machine: IntcodeMachine::new(program, Box::new(Vec::new()), Box::new(Vec::new())),
+            controller,
+            transcript: Vec::new(),
+            unseen: Vec::new(),
+            waiting: None,
+        }
+    }
+
+    pub fn machine(&self) -> &IntcodeMachine {
+        &self.machine
+    }
+
+    pub fn controller(&self) -> &C {
+        &self.controller
+    }
+
+    pub fn into_controller(self) -> C {
+        self.controller
+    }
+
+    pub fn transcript(&self) -> &[Exchange] {
+        &self.transcript
+    }
+
+    /// Every output in the transcript, in order.
+    pub fn outputs(&self) -> Vec<i32> {
+        self.transcript
+            .iter()
+            .filter_map(|e| match e {
+                Exchange::Output(x) => Some(*x),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Asks the controller for the input going to `dest`. False if it stopped instead.
+    fn feed(&mut self, dest: usize) -> Fallible<bool> {
+        let outputs: Vec<i32> = self.unseen.drain(..).collect();
+        match self.controller.decide(&outputs)? {
+            Decision::Input(value) => {
+                self.transcript.push(Exchange::Input(value));
+                self.machine.set_cell(dest, value)?;
+                self.waiting = None;
+                Ok(true)
+            }
+            Decision::Stop => {
+                self.waiting = Some(dest);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Runs until the program halts or the controller stops it.
+    pub fn run(&mut self) -> Fallible<Ending> {
+        if let Some(dest) = self.waiting {
+            if !self.feed(dest)? {
+                return Ok(Ending::Stopped);
+            }
+        }
+        loop {
+            match self.machine.unpack_op()? {
+                Opcode::Halt => {
+                    let outputs: Vec<i32> = self.unseen.drain(..).collect();
+                    self.controller.finish(&outputs)?;
+                    return Ok(Ending::Halted);
+                }
+                Opcode::Input { x } => {
+                    if !self.feed(x)? {
+                        return Ok(Ending::Stopped);
+                    }
+                }
+                Opcode::Output { x } => {
+                    let value = self.machine.value_at(&x)?;
+                    self.transcript.push(Exchange::Output(value));
+                    self.unseen.push(value);
+                }
+                op => op.execute(&mut self.machine)?,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOUBLER: &[i32] = &[
+        3, 15, // read n
+        1006, 15, 14, // n == 0 -> halt
+        1002, 15, 2, 15, // n *= 2
+        4, 15, // out n
+        1105, 1, 0, // loop
+        99, 0,
+    ];
+
+    #[test]
+    fn test_scripted() {
+        let mut session = Session::new(DOUBLER, Scripted::new(vec![1, 5, 21, 0]));
+        assert_eq!(session.run().unwrap(), Ending::Halted);
+        assert_eq!(session.outputs(), vec![2, 10, 42]);
+        assert_eq!(session.controller().outputs, vec![2, 10, 42]);
+        assert_eq!(
+            &session.transcript()[..3],
+            &[Exchange::Input(1), Exchange::Output(2), Exchange::Input(5)]
+        );
+    }
+
+    #[test]
+    fn test_stop_and_resume() {
+        let mut seen = Vec::new();
+        let mut next = 1;
+        let controller = move |outputs: &[i32]| -> Fallible<Decision> {
+            seen.extend_from_slice(outputs);
+            // react to the last output, and take a break once it gets big
+            match seen.last() {
+                Some(x) if *x >= 8 && next > 0 => {
+                    next = 0;
+                    Ok(Decision::Stop)
+                }
+                Some(x) if next == 0 => Ok(Decision::Input(if *x >= 8 { 0 } else { 1 })),
+                _ => {
+                    next *= 2;
+                    Ok(Decision::Input(next))
+                }
+            }
+        };
+        let mut session = Session::new(DOUBLER, controller);
+        assert_eq!(session.run().unwrap(), Ending::Stopped);
+        assert_eq!(session.outputs(), vec![4, 8]);
+        assert_eq!(session.run().unwrap(), Ending::Halted);
+        assert_eq!(
+            session.transcript(),
+            &[
+                Exchange::Input(2),
+                Exchange::Output(4),
+                Exchange::Input(4),
+                Exchange::Output(8),
+                Exchange::Input(0),
+            ]
+        );
+    }
+}
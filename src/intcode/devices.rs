@@ -0,0 +1,183 @@
+//! Devices that can be mapped over a range of addresses with
+//! [`IntcodeMachine::attach`](super::IntcodeMachine::attach).
+//!
+//! Once attached, any parameter that reads or writes a cell in the range talks to the device
+//! instead of memory. Machines without devices behave exactly as before.
+use super::grid::Grid;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub trait Device {
+    /// `offset` is relative to the start of the device's range.
+    fn read(&mut self, offset: usize) -> i32;
+    fn write(&mut self, offset: usize, value: i32);
+
+    /// Called once for every instruction the machine fetches.
+    fn tick(&mut self) {}
+}
+
+/// Counts instructions. Writing sets the count.
+#[derive(Debug, Default)]
+pub struct Timer {
+    ticks: i32,
+}
+
+impl Device for Timer {
+    fn read(&mut self, _offset: usize) -> i32 {
+        self.ticks
+    }
+
+    fn write(&mut self, _offset: usize, value: i32) {
+        self.ticks = value;
+    }
+
+    fn tick(&mut self) {
+        self.ticks = self.ticks.wrapping_add(1);
+    }
+}
+
+/// A fresh non-negative number on every read. Writing reseeds it.
+#[derive(Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // xorshift gets stuck on zero
+        Rng { state: seed.max(1) }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        // xorshift64
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// Something in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+impl Device for Rng {
+    fn read(&mut self, _offset: usize) -> i32 {
+        (self.next_u64() >> 33) as i32
+    }
+
+    fn write(&mut self, _offset: usize, value: i32) {
+        *self = Rng::new(value as u64);
+    }
+}
+
+/// Cells laid out row by row, `width` to a row. Hang on to [`grid`](Self::grid) to look at
+/// it after the device is attached.
+pub struct Framebuffer {
+    width: usize,
+    grid: Rc<RefCell<Grid>>,
+}
+
+impl Framebuffer {
+    /// Panics if `width` is 0, since then there are no rows to lay cells out in.
+    pub fn new(width: usize) -> Framebuffer {
+        assert!(width > 0, "a framebuffer needs a width of at least 1");
+        Framebuffer {
+            width,
+            grid: Rc::default(),
+        }
+    }
+
+    pub fn grid(&self) -> Rc<RefCell<Grid>> {
+        self.grid.clone()
+    }
+
+    fn position(&self, offset: usize) -> (i32, i32) {
+        ((offset % self.width) as i32, (offset / self.width) as i32)
+    }
+}
+
+impl Device for Framebuffer {
+    fn read(&mut self, offset: usize) -> i32 {
+        let (x, y) = self.position(offset);
+        self.grid.borrow().get(x, y).unwrap_or(0)
+    }
+
+    fn write(&mut self, offset: usize, value: i32) {
+        let (x, y) = self.position(offset);
+        self.grid.borrow_mut().set(x, y, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{IntcodeMachine, MachineError};
+    use super::*;
+
+    fn outputs(machine: IntcodeMachine) -> Vec<i32> {
+        machine.take_output().unwrap().results().unwrap()
+    }
+
+    #[test]
+    fn test_timer() {
+        // out timer, out timer, timer = 0, out timer
+        let data = &[4, 100, 4, 100, 1101, 0, 0, 100, 4, 100, 99];
+        let mut machine = IntcodeMachine::new(data, Box::new(Vec::new()), Box::new(Vec::new()));
+        machine
+            .attach(100..101, Box::new(Timer::default()))
+            .unwrap();
+        machine.run().unwrap();
+        assert_eq!(outputs(machine), vec![1, 2, 1]);
+    }
+
+    #[test]
+    fn test_rng_is_seeded() {
+        let data = &[4, 50, 4, 50, 4, 50, 99];
+        let run = |seed| {
+            let mut machine = IntcodeMachine::new(data, Box::new(Vec::new()), Box::new(Vec::new()));
+            machine.attach(50..51, Box::new(Rng::new(seed))).unwrap();
+            machine.run().unwrap();
+            outputs(machine)
+        };
+        let first = run(7);
+        assert_eq!(first, run(7));
+        assert_ne!(first, run(8));
+        assert!(first.iter().all(|x| *x >= 0));
+    }
+
+    #[test]
+    fn test_framebuffer() {
+        // fb[4] = 5, fb[8] = fb[4] * 2, out fb[8]
+        let data = &[1101, 5, 0, 204, 1002, 204, 2, 208, 4, 208, 99];
+        let framebuffer = Framebuffer::new(3);
+        let grid = framebuffer.grid();
+        let mut machine = IntcodeMachine::new(data, Box::new(Vec::new()), Box::new(Vec::new()));
+        machine.attach(200..209, Box::new(framebuffer)).unwrap();
+        machine.run().unwrap();
+        assert_eq!(grid.borrow().get(1, 1), Some(5));
+        assert_eq!(grid.borrow().get(2, 2), Some(10));
+        assert_eq!(machine.memory(), data);
+        assert_eq!(outputs(machine), vec![10]);
+    }
+
+    #[test]
+    fn test_overlap_is_rejected() {
+        let mut machine = IntcodeMachine::new(&[99], Box::new(Vec::new()), Box::new(Vec::new()));
+        machine.attach(10..20, Box::new(Timer::default())).unwrap();
+        machine.attach(20..30, Box::new(Timer::default())).unwrap();
+        let err = machine
+            .attach(5..11, Box::new(Timer::default()))
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<MachineError>(),
+            Some(MachineError::DeviceOverlap(10))
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "width of at least 1")]
+    fn test_framebuffer_needs_width() {
+        Framebuffer::new(0);
+    }
+}
@@ -0,0 +1,274 @@
+//! Runs one program on two interpreters in lockstep and reports the first place they disagree.
+//!
+//! Anything that implements [`Vm`] can take part, so besides the [`legacy`](super::legacy)
+//! machine against the current one this also covers the current machine against itself on two
+//! versions of an image, like before and after [`optimize`](super::optimize::optimize).
+use super::legacy;
+use super::{Instruction, IntcodeMachine, MachineError, Opcode};
+use failure::Fallible;
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::panic::{self, AssertUnwindSafe};
+
+pub trait Vm {
+    /// Runs one instruction. `Ok(false)` once the machine has reached a halt.
+    fn step(&mut self) -> Fallible<bool>;
+    fn ip(&self) -> usize;
+    fn memory(&self) -> &[i32];
+    fn outputs(&self) -> &[i32];
+}
+
+impl Vm for legacy::IntcodeMachine {
+    fn step(&mut self) -> Fallible<bool> {
+        match self.read_op()? {
+            legacy::Opcode::Halt => Ok(false),
+            op => {
+                legacy::Instruction::execute(op, self);
+                Ok(true)
+            }
+        }
+    }
+
+    fn ip(&self) -> usize {
+        self.ip()
+    }
+
+    fn memory(&self) -> &[i32] {
+        self.memory()
+    }
+
+    fn outputs(&self) -> &[i32] {
+        &[]
+    }
+}
+
+/// The current machine, fed from a fixed list of inputs.
+pub struct Current {
+    machine: IntcodeMachine,
+    inputs: VecDeque<i32>,
+    outputs: Vec<i32>,
+}
+
+impl Current {
+    pub fn new(program: &[i32], inputs: Vec<i32>) -> Current {
+        Current {
+            // I/O never reaches the machine, step() intercepts it
+            machine: IntcodeMachine::new(program, Box::new(Vec::new()), Box::new(Vec::new())),
+            inputs: inputs.into(),
+            outputs: Vec::new(),
+        }
+    }
+}
+
+impl Vm for Current {
+    fn step(&mut self) -> Fallible<bool> {
+        match self.machine.unpack_op()? {
+            Opcode::Halt => return Ok(false),
+            Opcode::Input { x } => {
+                let value = self.inputs.pop_front().ok_or(MachineError::EOF)?;
+                self.machine.set_cell(x, value)?;
+            }
+            Opcode::Output { x } => {
+                let value = self.machine.value_at(&x)?;
+                self.outputs.push(value);
+            }
+            op => op.execute(&mut self.machine)?,
+        }
+        Ok(true)
+    }
+
+    fn ip(&self) -> usize {
+        self.machine.ip()
+    }
+
+    fn memory(&self) -> &[i32] {
+        self.machine.memory()
+    }
+
+    fn outputs(&self) -> &[i32] {
+        &self.outputs
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+    Running,
+    Halted,
+    /// Returned an error or panicked. The message isn't compared, every generation words its
+    /// errors differently.
+    Failed(String),
+}
+
+impl Display for Status {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        match self {
+            Status::Running => write!(w, "running"),
+            Status::Halted => write!(w, "halted"),
+            Status::Failed(e) => write!(w, "failed ({})", e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Difference {
+    Status(Status, Status),
+    Ip(usize, usize),
+    /// The first cell that differs. `None` is past the end of that machine's memory.
+    Memory {
+        addr: usize,
+        a: Option<i32>,
+        b: Option<i32>,
+    },
+    Output(Vec<i32>, Vec<i32>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// Instructions both machines had run, counting the one that split them.
+    pub step: usize,
+    pub difference: Difference,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        write!(w, "step {}: ", self.step)?;
+        match &self.difference {
+            Difference::Status(a, b) => write!(w, "{} vs {}", a, b),
+            Difference::Ip(a, b) => write!(w, "ip {} vs {}", a, b),
+            Difference::Memory { addr, a, b } => {
+                let cell = |x: &Option<i32>| x.map_or("nothing".to_string(), |x| x.to_string());
+                write!(w, "mem[{}] {} vs {}", addr, cell(a), cell(b))
+            }
+            Difference::Output(a, b) => write!(w, "output {:?} vs {:?}", a, b),
+        }
+    }
+}
+
+/// Runs `f`, turning a panic into its message.
+pub fn catch_panic<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|panic| {
+        let message = panic
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_default();
+        format!("panicked: {}", message)
+    })
+}
+
+/// Runs one instruction, whatever happens.
+pub fn step(vm: &mut dyn Vm) -> Status {
+    match catch_panic(|| vm.step()) {
+        Ok(Ok(true)) => Status::Running,
+        Ok(Ok(false)) => Status::Halted,
+        Ok(Err(e)) => Status::Failed(e.to_string()),
+        Err(message) => Status::Failed(message),
+    }
+}
+
+fn differ(a: &dyn Vm, b: &dyn Vm) -> Option<Difference> {
+    if a.ip() != b.ip() {
+        return Some(Difference::Ip(a.ip(), b.ip()));
+    }
+    let (ma, mb) = (a.memory(), b.memory());
+    if ma != mb {
+        let addr = (0..ma.len().max(mb.len()))
+            .find(|i| ma.get(*i) != mb.get(*i))
+            .unwrap();
+        return Some(Difference::Memory {
+            addr,
+            a: ma.get(addr).cloned(),
+            b: mb.get(addr).cloned(),
+        });
+    }
+    if a.outputs() != b.outputs() {
+        return Some(Difference::Output(
+            a.outputs().to_vec(),
+            b.outputs().to_vec(),
+        ));
+    }
+    None
+}
+
+/// Steps both machines together until they halt, fail, or run `max_steps` instructions.
+/// Failing together counts as agreeing.
+pub fn compare(a: &mut dyn Vm, b: &mut dyn Vm, max_steps: usize) -> Option<Divergence> {
+    let diverged = |step, difference| Some(Divergence { step, difference });
+    if let Some(difference) = differ(a, b) {
+        return diverged(0, difference);
+    }
+    for n in 1..=max_steps {
+        let (sa, sb) = (step(a), step(b));
+        match (&sa, &sb) {
+            (Status::Failed(_), Status::Failed(_)) => return None,
+            _ if sa != sb => return diverged(n, Difference::Status(sa, sb)),
+            _ => {}
+        }
+        if let Some(difference) = differ(a, b) {
+            return diverged(n, difference);
+        }
+        if sa == Status::Halted {
+            return None;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::devices::Rng;
+    use super::super::fuzz::{Case, Config};
+    use super::*;
+
+    fn generations(program: &[i32]) -> Option<Divergence> {
+        let mut old = legacy::IntcodeMachine::new(program);
+        let mut new = Current::new(program, Vec::new());
+        compare(&mut old, &mut new, 10_000)
+    }
+
+    #[test]
+    fn test_generations_agree_on_day2_programs() {
+        assert_eq!(
+            generations(&[1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]),
+            None
+        );
+        let config = Config {
+            instructions: 8,
+            data: 4,
+            day2: true,
+            ..Config::default()
+        };
+        for seed in 1..200 {
+            let program = Case::generate(&mut Rng::new(seed), &config).image();
+            assert_eq!(generations(&program), None, "seed {}: {:?}", seed, program);
+        }
+    }
+
+    #[test]
+    fn test_modes_diverge() {
+        // only the current machine knows 1101 is an add of two immediates
+        let divergence = generations(&[1101, 2, 3, 5, 99, 0]).unwrap();
+        assert_eq!(divergence.step, 1);
+        assert!(matches!(
+            divergence.difference,
+            Difference::Status(Status::Failed(_), Status::Running)
+        ));
+    }
+
+    #[test]
+    fn test_first_memory_difference() {
+        let mut a = Current::new(&[1101, 2, 3, 7, 4, 7, 99, 0], Vec::new());
+        let mut b = Current::new(&[1101, 2, 4, 7, 4, 7, 99, 0], Vec::new());
+        let divergence = compare(&mut a, &mut b, 100).unwrap();
+        assert_eq!(divergence.to_string(), "step 0: mem[2] 3 vs 4");
+    }
+
+    #[test]
+    fn test_different_inputs() {
+        let mut a = Current::new(&[3, 5, 4, 5, 99, 0], vec![1]);
+        let mut b = Current::new(&[3, 5, 4, 5, 99, 0], vec![2]);
+        let divergence = compare(&mut a, &mut b, 100).unwrap();
+        assert_eq!(divergence.step, 1);
+        assert_eq!(divergence.to_string(), "step 1: mem[5] 1 vs 2");
+    }
+}
@@ -0,0 +1,471 @@
+//! Random well-formed Intcode programs for property tests, with shrinking.
+//!
+//! A [`Case`] keeps its code as decoded instructions, so shrinking can drop or simplify whole
+//! instructions and then [`repair`](Case::repair) the addresses back into bounds. A property
+//! gets a case and says what went wrong, if anything. [`check`] runs a batch of cases and
+//! shrinks the first failure it finds.
+use super::devices::Rng;
+use super::differential::{catch_panic, compare, step, Current, Status, Vm};
+use super::optimize::optimize;
+use super::{legacy, Instruction, IntcodeMachine, Opcode, Parameter};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// How long a property lets a program run before calling it a day.
+const MAX_STEPS: usize = 10_000;
+
+/// Shrinking gives up after this many successful steps.
+const MAX_SHRINKS: usize = 1000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Not counting the halt every program ends with.
+    pub instructions: usize,
+    pub data: usize,
+    pub inputs: usize,
+    /// Only position-mode `Add` and `Mul` writing into the data, which the
+    /// [`legacy`](super::legacy) machine understands too.
+    pub day2: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            instructions: 12,
+            data: 8,
+            inputs: 4,
+            day2: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Case {
+    pub code: Vec<Opcode>,
+    /// Laid out right after the code.
+    pub data: Vec<i32>,
+    pub inputs: Vec<i32>,
+}
+
+/// Mostly small numbers, now and then a big one to find overflows.
+fn value(rng: &mut Rng) -> i32 {
+    if rng.below(10) == 0 {
+        (rng.next_u64() >> 33) as i32
+    } else {
+        rng.below(20) as i32 - 5
+    }
+}
+
+/// Addresses start out anywhere and get pulled into bounds by `repair`.
+fn param(rng: &mut Rng) -> Parameter {
+    if rng.below(2) == 0 {
+        Parameter::Immediate(value(rng))
+    } else {
+        Parameter::Indirect(rng.below(1000))
+    }
+}
+
+fn target(rng: &mut Rng) -> Parameter {
+    if rng.below(8) == 0 {
+        Parameter::Indirect(rng.below(1000))
+    } else {
+        Parameter::Immediate(rng.below(1000) as i32)
+    }
+}
+
+impl Case {
+    pub fn generate(rng: &mut Rng, config: &Config) -> Case {
+        let mut code = Vec::with_capacity(config.instructions + 1);
+        let code_len = if config.day2 {
+            config.instructions * 4 + 1
+        } else {
+            0
+        };
+        for _ in 0..config.instructions {
+            let op = if config.day2 {
+                let x = Parameter::Indirect(rng.below(code_len + config.data));
+                let y = Parameter::Indirect(rng.below(code_len + config.data));
+                let dest = code_len + rng.below(config.data.max(1));
+                if rng.below(2) == 0 {
+                    Opcode::Add { x, y, dest }
+                } else {
+                    Opcode::Mul { x, y, dest }
+                }
+            } else {
+                match rng.below(17) {
+                    0..=1 => Opcode::Add {
+                        x: param(rng),
+                        y: param(rng),
+                        dest: rng.below(1000),
+                    },
+                    2..=3 => Opcode::Mul {
+                        x: param(rng),
+                        y: param(rng),
+                        dest: rng.below(1000),
+                    },
+                    4..=5 => Opcode::Input { x: rng.below(1000) },
+                    6..=7 => Opcode::Output { x: param(rng) },
+                    8..=9 => Opcode::JumpIfTrue {
+                        x: param(rng),
+                        dest: target(rng),
+                    },
+                    10..=11 => Opcode::JumpIfFalse {
+                        x: param(rng),
+                        dest: target(rng),
+                    },
+                    12..=13 => Opcode::LessThan {
+                        x: param(rng),
+                        y: param(rng),
+                        dest: rng.below(1000),
+                    },
+                    14..=15 => Opcode::Equal {
+                        x: param(rng),
+                        y: param(rng),
+                        dest: rng.below(1000),
+                    },
+                    _ => Opcode::Halt,
+                }
+            };
+            code.push(op);
+        }
+        code.push(Opcode::Halt);
+        let mut case = Case {
+            code,
+            data: (0..config.data).map(|_| value(rng)).collect(),
+            inputs: (0..config.inputs).map(|_| rng.below(20) as i32).collect(),
+        };
+        case.repair();
+        case
+    }
+
+    fn starts(&self) -> Vec<usize> {
+        let mut addr = 0;
+        self.code
+            .iter()
+            .map(|op| {
+                addr += op.len();
+                addr - op.len()
+            })
+            .collect()
+    }
+
+    fn image_len(&self) -> usize {
+        self.code.iter().map(Opcode::len).sum::<usize>() + self.data.len()
+    }
+
+    pub fn image(&self) -> Vec<i32> {
+        self.code
+            .iter()
+            .flat_map(Opcode::encode)
+            .chain(self.data.iter().cloned())
+            .collect()
+    }
+
+    /// Pulls every address back inside the image and every immediate jump onto an instruction.
+    pub fn repair(&mut self) {
+        let len = self.image_len();
+        let starts = self.starts();
+        if starts.is_empty() {
+            return;
+        }
+        let fix = |addr: usize| if addr < len { addr } else { addr % len };
+        let fix_param = |p: Parameter| match p {
+            Parameter::Indirect(addr) => Parameter::Indirect(fix(addr)),
+            p => p,
+        };
+        let fix_target = |p: Parameter| match p {
+            Parameter::Immediate(d) if !starts.contains(&(d as usize)) => {
+                Parameter::Immediate(starts[d.unsigned_abs() as usize % starts.len()] as i32)
+            }
+            p => fix_param(p),
+        };
+        for op in self.code.iter_mut() {
+            *op = match *op {
+                Opcode::Add { x, y, dest } => Opcode::Add {
+                    x: fix_param(x),
+                    y: fix_param(y),
+                    dest: fix(dest),
+                },
+                Opcode::Mul { x, y, dest } => Opcode::Mul {
+                    x: fix_param(x),
+                    y: fix_param(y),
+                    dest: fix(dest),
+                },
+                Opcode::LessThan { x, y, dest } => Opcode::LessThan {
+                    x: fix_param(x),
+                    y: fix_param(y),
+                    dest: fix(dest),
+                },
+                Opcode::Equal { x, y, dest } => Opcode::Equal {
+                    x: fix_param(x),
+                    y: fix_param(y),
+                    dest: fix(dest),
+                },
+                Opcode::Input { x } => Opcode::Input { x: fix(x) },
+                Opcode::Output { x } => Opcode::Output { x: fix_param(x) },
+                Opcode::JumpIfTrue { x, dest } => Opcode::JumpIfTrue {
+                    x: fix_param(x),
+                    dest: fix_target(dest),
+                },
+                Opcode::JumpIfFalse { x, dest } => Opcode::JumpIfFalse {
+                    x: fix_param(x),
+                    dest: fix_target(dest),
+                },
+                Opcode::Halt => Opcode::Halt,
+            }
+        }
+    }
+
+    /// Slightly simpler versions of this case, most drastic first.
+    fn shrinks(&self) -> Vec<Case> {
+        let mut candidates = Vec::new();
+        let mut with = |f: &dyn Fn(&mut Case)| {
+            let mut case = self.clone();
+            f(&mut case);
+            case.repair();
+            candidates.push(case);
+        };
+        for i in 0..self.code.len() {
+            if self.code.len() > 1 {
+                with(&|c: &mut Case| {
+                    c.code.remove(i);
+                });
+            }
+            if self.code[i] != Opcode::Halt {
+                with(&|c: &mut Case| c.code[i] = Opcode::Halt);
+            }
+        }
+        for i in 0..self.data.len() {
+            with(&|c: &mut Case| {
+                c.data.remove(i);
+            });
+        }
+        for i in 0..self.inputs.len() {
+            with(&|c: &mut Case| {
+                c.inputs.remove(i);
+            });
+        }
+        let smaller = |v: i32| match v {
+            0 => vec![],
+            -1 | 1 => vec![0],
+            v => vec![0, v / 2],
+        };
+        for i in 0..self.data.len() {
+            for v in smaller(self.data[i]) {
+                with(&|c: &mut Case| c.data[i] = v);
+            }
+        }
+        for i in 0..self.inputs.len() {
+            for v in smaller(self.inputs[i]) {
+                with(&|c: &mut Case| c.inputs[i] = v);
+            }
+        }
+        for i in 0..self.code.len() {
+            let mut cells = self.code[i].encode();
+            for j in 1..cells.len() {
+                // only immediates, addresses are repair's business
+                if (cells[0] / 10_i32.pow(j as u32 + 1)) % 10 != 1 {
+                    continue;
+                }
+                for v in smaller(cells[j]) {
+                    cells[j] = v;
+                    if let Ok(op) = Opcode::decode(&cells, 0) {
+                        with(&|c: &mut Case| c.code[i] = op);
+                    }
+                    cells = self.code[i].encode();
+                }
+            }
+        }
+        candidates
+    }
+}
+
+#[derive(Debug)]
+pub struct Failure {
+    /// Seed for the case as generated, before shrinking.
+    pub seed: u64,
+    pub case: Case,
+    pub message: String,
+}
+
+impl Display for Failure {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        writeln!(w, "seed {}: {}", self.seed, self.message)?;
+        writeln!(w, "program: {:?}", self.case.image())?;
+        write!(w, "inputs: {:?}", self.case.inputs)
+    }
+}
+
+/// Tries `property` on `cases` programs, seeded from `seed` on. The first failure comes back
+/// shrunk as far as it goes.
+pub fn check<P>(seed: u64, cases: u64, config: &Config, property: P) -> Result<(), Failure>
+where
+    P: Fn(&Case) -> Result<(), String>,
+{
+    for seed in seed..seed + cases {
+        let mut case = Case::generate(&mut Rng::new(seed), config);
+        let mut message = match property(&case) {
+            Ok(()) => continue,
+            Err(message) => message,
+        };
+        'shrinking: for _ in 0..MAX_SHRINKS {
+            for candidate in case.shrinks() {
+                if let Err(m) = property(&candidate) {
+                    case = candidate;
+                    message = m;
+                    continue 'shrinking;
+                }
+            }
+            break;
+        }
+        return Err(Failure {
+            seed,
+            case,
+            message,
+        });
+    }
+    Ok(())
+}
+
+/// Running the program can fail, but never panic.
+pub fn never_panics(case: &Case) -> Result<(), String> {
+    let mut inputs = case.inputs.clone();
+    // Vec<i32> hands out inputs from the back
+    inputs.reverse();
+    let mut machine = IntcodeMachine::new(&case.image(), Box::new(inputs), Box::new(Vec::new()));
+    catch_panic(|| {
+        for _ in 0..MAX_STEPS {
+            match machine.unpack_op() {
+                Ok(Opcode::Halt) | Err(_) => return,
+                Ok(op) => {
+                    if op.execute(&mut machine).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Decoding the image gives back the code, and encoding that gives back the image.
+pub fn round_trips(case: &Case) -> Result<(), String> {
+    let image = case.image();
+    let mut addr = 0;
+    for op in case.code.iter() {
+        let decoded = Opcode::decode(&image, addr).map_err(|e| format!("{}: {}", addr, e))?;
+        if decoded != *op {
+            return Err(format!("{}: decoded {} but wrote {}", addr, decoded, op));
+        }
+        if decoded.encode()[..] != image[addr..addr + op.len()] {
+            return Err(format!("{}: {} encodes differently", addr, op));
+        }
+        addr += op.len();
+    }
+    Ok(())
+}
+
+fn finish(vm: &mut dyn Vm) -> Option<(Status, Vec<i32>)> {
+    for _ in 0..MAX_STEPS {
+        match step(vm) {
+            Status::Running => {}
+            Status::Halted => return Some((Status::Halted, vm.outputs().to_vec())),
+            // error messages aren't part of the contract
+            Status::Failed(_) => {
+                return Some((Status::Failed(String::new()), vm.outputs().to_vec()))
+            }
+        }
+    }
+    None
+}
+
+/// The optimized image ends the same way and outputs the same things. Programs still running
+/// after the step limit prove nothing either way.
+pub fn optimizer_agrees(case: &Case) -> Result<(), String> {
+    let image = case.image();
+    let optimized = optimize(&image).program;
+    let before = finish(&mut Current::new(&image, case.inputs.clone()));
+    let after = finish(&mut Current::new(&optimized, case.inputs.clone()));
+    match (before, after) {
+        (Some(before), Some(after)) if before != after => {
+            Err(format!("{:?} became {:?}", before, after))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The day 2 machine and the current one agree step by step. Only meaningful for
+/// [`Config::day2`] cases.
+pub fn generations_agree(case: &Case) -> Result<(), String> {
+    let image = case.image();
+    let mut old = legacy::IntcodeMachine::new(&image);
+    let mut new = Current::new(&image, case.inputs.clone());
+    match compare(&mut old, &mut new, MAX_STEPS) {
+        Some(divergence) => Err(divergence.to_string()),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holds(config: &Config, property: impl Fn(&Case) -> Result<(), String>) {
+        if let Err(failure) = check(1, 500, config, property) {
+            panic!("{}", failure);
+        }
+    }
+
+    #[test]
+    fn test_generated_programs_are_well_formed() {
+        holds(&Config::default(), round_trips);
+        holds(&Config::default(), |case| {
+            let len = case.image().len();
+            let out_of_bounds = case.code.iter().any(|op| match op.encode()[..] {
+                [op, ref params @ ..] => params
+                    .iter()
+                    .enumerate()
+                    .any(|(i, p)| (op / 10_i32.pow(i as u32 + 2)) % 10 == 0 && *p as usize >= len),
+                [] => false,
+            });
+            if out_of_bounds {
+                Err("address out of bounds".to_string())
+            } else {
+                Ok(())
+            }
+        });
+    }
+
+    #[test]
+    fn test_shrinking() {
+        let no_mul = |case: &Case| {
+            if case.code.iter().any(|op| matches!(op, Opcode::Mul { .. })) {
+                Err("found a mul".to_string())
+            } else {
+                Ok(())
+            }
+        };
+        let failure = check(1, 100, &Config::default(), no_mul).unwrap_err();
+        assert_eq!(failure.case.code.len(), 1);
+        assert!(matches!(failure.case.code[0], Opcode::Mul { .. }));
+        assert!(failure.case.data.is_empty());
+        assert!(failure.case.inputs.is_empty());
+    }
+
+    #[test]
+    fn test_never_panics() {
+        holds(&Config::default(), never_panics);
+    }
+
+    #[test]
+    fn test_optimizer_agrees() {
+        holds(&Config::default(), optimizer_agrees);
+    }
+
+    #[test]
+    fn test_generations_agree() {
+        let config = Config {
+            day2: true,
+            ..Config::default()
+        };
+        holds(&config, generations_agree);
+    }
+}
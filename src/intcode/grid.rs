@@ -0,0 +1,432 @@
+//! Sparse 2D grids for programs that draw, either as a screen of `(x, y, tile)` triples or as a
+//! robot painting the panel under it.
+//!
+//! Both adapters share their [`Grid`] through an `Rc<RefCell<_>>`, so grab a handle with
+//! `grid()` before boxing them up for [`IntcodeMachine::new`](super::IntcodeMachine::new).
+use super::{Input, Output};
+use failure::Fallible;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::time::Duration;
+
+mod png;
+
+/// Most pixels (or characters, for [`Grid::render`]) we're willing to draw. A stray output
+/// far from the rest would otherwise ask for gigabytes.
+pub const MAX_PIXELS: i64 = 1 << 24;
+
+/// Characters for the usual tiles: empty, wall, block, paddle, ball.
+pub fn default_palette(tile: i32) -> char {
+    match tile {
+        0 => ' ',
+        1 => '#',
+        2 => '+',
+        3 => '-',
+        4 => 'o',
+        _ => '?',
+    }
+}
+
+/// Colours matching [`default_palette`], with empty as black and wall as white.
+pub fn default_colors(tile: i32) -> [u8; 3] {
+    match tile {
+        0 => [0, 0, 0],
+        1 => [255, 255, 255],
+        2 => [200, 120, 40],
+        3 => [40, 120, 200],
+        4 => [220, 40, 40],
+        _ => [255, 0, 255],
+    }
+}
+
+/// A grid whose picture would be larger than [`MAX_PIXELS`]. Sizes that don't fit in an `i64`
+/// show up as `i64::MAX`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TooLarge {
+    pub width: i64,
+    pub height: i64,
+}
+
+impl Display for TooLarge {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        write!(
+            w,
+            "a {}x{} image is larger than {} pixels",
+            self.width, self.height, MAX_PIXELS
+        )
+    }
+}
+
+impl std::error::Error for TooLarge {}
+
+impl From<TooLarge> for io::Error {
+    fn from(e: TooLarge) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidInput, e)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Grid {
+    cells: HashMap<(i32, i32), i32>,
+}
+
+impl Grid {
+    pub fn get(&self, x: i32, y: i32) -> Option<i32> {
+        self.cells.get(&(x, y)).cloned()
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, tile: i32) {
+        self.cells.insert((x, y), tile);
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn count(&self, tile: i32) -> usize {
+        self.cells.values().filter(|t| **t == tile).count()
+    }
+
+    /// Smallest and largest corner of everything drawn so far.
+    pub fn bounds(&self) -> Option<((i32, i32), (i32, i32))> {
+        let xs = self.cells.keys().map(|(x, _)| *x);
+        let ys = self.cells.keys().map(|(_, y)| *y);
+        Some((
+            (xs.clone().min()?, ys.clone().min()?),
+            (xs.max()?, ys.max()?),
+        ))
+    }
+
+    /// Width and height of the picture with each cell `scale` pixels square, checked against
+    /// [`MAX_PIXELS`]. An empty grid is drawn as a single cell.
+    pub fn size(&self, scale: usize) -> Result<(usize, usize), TooLarge> {
+        let ((min_x, min_y), (max_x, max_y)) = self.bounds().unwrap_or(((0, 0), (0, 0)));
+        let scale = i64::try_from(scale).unwrap_or(i64::MAX);
+        let side = |low: i32, high: i32| {
+            (i64::from(high) - i64::from(low) + 1)
+                .checked_mul(scale)
+                .unwrap_or(i64::MAX)
+        };
+        let (width, height) = (side(min_x, max_x), side(min_y, max_y));
+        match width.checked_mul(height) {
+            Some(pixels) if pixels <= MAX_PIXELS => Ok((width as usize, height as usize)),
+            _ => Err(TooLarge { width, height }),
+        }
+    }
+
+    /// One line per row, top to bottom. Cells never drawn count as tile 0.
+    pub fn render(&self, palette: impl Fn(i32) -> char) -> Result<String, TooLarge> {
+        self.size(1)?;
+        let mut out = String::new();
+        if let Some(((min_x, min_y), (max_x, max_y))) = self.bounds() {
+            for y in min_y..=max_y {
+                let line: String = (min_x..=max_x)
+                    .map(|x| palette(self.get(x, y).unwrap_or(0)))
+                    .collect();
+                out.push_str(line.trim_end());
+                out.push('\n');
+            }
+        }
+        Ok(out)
+    }
+
+    /// Calls `f` with each row of RGB pixels, top to bottom, each cell `scale` pixels square.
+    fn pixels<F>(&self, scale: usize, colors: impl Fn(i32) -> [u8; 3], mut f: F) -> io::Result<()>
+    where
+        F: FnMut(&[u8]) -> io::Result<()>,
+    {
+        let ((min_x, min_y), (max_x, max_y)) = self.bounds().unwrap_or(((0, 0), (0, 0)));
+        let (width, _) = self.size(scale)?;
+        for y in min_y..=max_y {
+            let mut row = Vec::with_capacity(width * 3);
+            for x in min_x..=max_x {
+                let rgb = colors(self.get(x, y).unwrap_or(0));
+                for _ in 0..scale {
+                    row.extend_from_slice(&rgb);
+                }
+            }
+            for _ in 0..scale {
+                f(&row)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a binary (P6) PPM, each cell `scale` pixels square.
+    pub fn write_ppm<W: Write>(
+        &self,
+        mut w: W,
+        scale: usize,
+        colors: impl Fn(i32) -> [u8; 3],
+    ) -> io::Result<()> {
+        let (width, height) = self.size(scale)?;
+        write!(w, "P6\n{} {}\n255\n", width, height)?;
+        self.pixels(scale, colors, |row| w.write_all(row))
+    }
+
+    /// Writes an RGB PNG, each cell `scale` pixels square.
+    pub fn write_png<W: Write>(
+        &self,
+        w: W,
+        scale: usize,
+        colors: impl Fn(i32) -> [u8; 3],
+    ) -> io::Result<()> {
+        let (width, height) = self.size(scale)?;
+        let mut rows = Vec::with_capacity((width * 3 + 1) * height);
+        self.pixels(scale, colors, |row| {
+            // filter type 0: the row as is
+            rows.push(0);
+            rows.extend_from_slice(row);
+            Ok(())
+        })?;
+        png::write(w, width as u32, height as u32, &rows)
+    }
+}
+
+/// Collects `(x, y, tile)` output triples into a grid.
+#[derive(Default)]
+pub struct Screen {
+    grid: Rc<RefCell<Grid>>,
+    pending: Vec<i32>,
+    live: Option<Duration>,
+}
+
+impl Screen {
+    pub fn new() -> Screen {
+        Screen::default()
+    }
+
+    /// Redraws the grid on the terminal after every tile, pausing `delay` each frame.
+    pub fn live(mut self, delay: Duration) -> Screen {
+        self.live = Some(delay);
+        self
+    }
+
+    pub fn grid(&self) -> Rc<RefCell<Grid>> {
+        self.grid.clone()
+    }
+}
+
+impl Output for Screen {
+    fn output(&mut self, what: i32) -> Fallible<()> {
+        self.pending.push(what);
+        if let [x, y, tile] = self.pending[..] {
+            self.pending.clear();
+            self.grid.borrow_mut().set(x, y, tile);
+            if let Some(delay) = self.live {
+                let frame = self.grid.borrow().render(default_palette)?;
+                let mut stdout = io::stdout();
+                // clear the screen and home the cursor
+                write!(stdout, "\x1b[2J\x1b[H{}", frame)?;
+                stdout.flush()?;
+                std::thread::sleep(delay);
+            }
+        }
+        Ok(())
+    }
+
+    fn results(&self) -> Option<Vec<i32>> {
+        None
+    }
+}
+
+struct Turtle {
+    position: (i32, i32),
+    /// Unit vector, with y growing downwards like the screen.
+    heading: (i32, i32),
+    painted: HashSet<(i32, i32)>,
+    paint_next: bool,
+}
+
+/// A robot that reports the colour under it and takes `(colour, turn)` pairs, turning left on
+/// 0 and right on 1 before stepping forward. Starts facing up at the origin.
+pub struct PaintingRobot {
+    grid: Rc<RefCell<Grid>>,
+    turtle: Rc<RefCell<Turtle>>,
+}
+
+impl PaintingRobot {
+    /// `start` is the colour of the panel the robot starts on.
+    pub fn new(start: i32) -> PaintingRobot {
+        let mut grid = Grid::default();
+        grid.set(0, 0, start);
+        PaintingRobot {
+            grid: Rc::new(RefCell::new(grid)),
+            turtle: Rc::new(RefCell::new(Turtle {
+                position: (0, 0),
+                heading: (0, -1),
+                painted: HashSet::new(),
+                paint_next: true,
+            })),
+        }
+    }
+
+    pub fn grid(&self) -> Rc<RefCell<Grid>> {
+        self.grid.clone()
+    }
+
+    /// How many distinct panels have been painted at least once.
+    pub fn painted(&self) -> usize {
+        self.turtle.borrow().painted.len()
+    }
+
+    pub fn camera(&self) -> Camera {
+        Camera {
+            grid: self.grid.clone(),
+            turtle: self.turtle.clone(),
+        }
+    }
+
+    pub fn painter(&self) -> Painter {
+        Painter {
+            grid: self.grid.clone(),
+            turtle: self.turtle.clone(),
+        }
+    }
+}
+
+pub struct Camera {
+    grid: Rc<RefCell<Grid>>,
+    turtle: Rc<RefCell<Turtle>>,
+}
+
+impl Input for Camera {
+    fn input(&mut self) -> Fallible<i32> {
+        let (x, y) = self.turtle.borrow().position;
+        Ok(self.grid.borrow().get(x, y).unwrap_or(0))
+    }
+}
+
+pub struct Painter {
+    grid: Rc<RefCell<Grid>>,
+    turtle: Rc<RefCell<Turtle>>,
+}
+
+impl Output for Painter {
+    fn output(&mut self, what: i32) -> Fallible<()> {
+        let mut turtle = self.turtle.borrow_mut();
+        if turtle.paint_next {
+            let (x, y) = turtle.position;
+            self.grid.borrow_mut().set(x, y, what);
+            turtle.painted.insert((x, y));
+        } else {
+            let (dx, dy) = turtle.heading;
+            turtle.heading = if what == 0 { (dy, -dx) } else { (-dy, dx) };
+            let (dx, dy) = turtle.heading;
+            turtle.position = (turtle.position.0 + dx, turtle.position.1 + dy);
+        }
+        turtle.paint_next = !turtle.paint_next;
+        Ok(())
+    }
+
+    fn results(&self) -> Option<Vec<i32>> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::IntcodeMachine;
+    use super::*;
+
+    #[test]
+    fn test_screen_collects_triples() {
+        let data = &[
+            104, 1, 104, 2, 104, 3, // (1, 2) = paddle
+            104, 6, 104, 5, 104, 4, // (6, 5) = ball
+            104, 1, 104, 2, 104, 1, // (1, 2) = wall
+            99,
+        ];
+        let screen = Screen::new();
+        let grid = screen.grid();
+        let mut machine = IntcodeMachine::new(data, Box::new(Vec::new()), Box::new(screen));
+        machine.run().unwrap();
+        let grid = grid.borrow();
+        assert_eq!(grid.len(), 2);
+        assert_eq!(grid.get(1, 2), Some(1));
+        assert_eq!(grid.bounds(), Some(((1, 2), (6, 5))));
+        assert_eq!(grid.render(default_palette).unwrap(), "#\n\n\n     o\n");
+    }
+
+    #[test]
+    fn test_ppm_header_and_size() {
+        let mut grid = Grid::default();
+        grid.set(0, 0, 1);
+        grid.set(2, 1, 1);
+        let mut buf = Vec::new();
+        grid.write_ppm(&mut buf, 2, default_colors).unwrap();
+        let header = b"P6\n6 4\n255\n";
+        assert_eq!(&buf[..header.len()], &header[..]);
+        assert_eq!(buf.len(), header.len() + 6 * 4 * 3);
+        assert_eq!(&buf[header.len()..header.len() + 3], &[255, 255, 255]);
+    }
+
+    #[test]
+    fn test_png() {
+        let mut grid = Grid::default();
+        grid.set(0, 0, 1);
+        grid.set(2, 1, 4);
+        let mut buf = Vec::new();
+        grid.write_png(&mut buf, 2, default_colors).unwrap();
+        assert_eq!(&buf[..8], b"\x89PNG\r\n\x1a\n");
+        // IHDR: 6x4, 8 bits per channel, RGB
+        assert_eq!(&buf[12..16], b"IHDR");
+        assert_eq!(&buf[16..29], &[0, 0, 0, 6, 0, 0, 0, 4, 8, 2, 0, 0, 0]);
+        assert!(buf.ends_with(b"IEND\xae\x42\x60\x82"));
+        let pixels = png::decode(&buf).unwrap();
+        assert_eq!(pixels.len(), 4 * (1 + 6 * 3));
+        assert_eq!(&pixels[..4], &[0, 255, 255, 255]);
+        assert_eq!(&pixels[3 * 19 - 6..3 * 19], &[220, 40, 40, 220, 40, 40]);
+    }
+
+    #[test]
+    fn test_too_large() {
+        let mut grid = Grid::default();
+        grid.set(i32::MIN, 0, 1);
+        grid.set(i32::MAX, 0, 1);
+        let too_large = TooLarge {
+            width: 1 << 32,
+            height: 1,
+        };
+        assert_eq!(grid.size(1), Err(too_large));
+        assert_eq!(grid.render(default_palette), Err(too_large));
+        let error = grid.write_ppm(Vec::new(), 1, default_colors).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(grid.write_png(Vec::new(), 1, default_colors).is_err());
+
+        let mut grid = Grid::default();
+        grid.set(0, 0, 1);
+        assert_eq!(grid.size(4096), Ok((4096, 4096)));
+        assert!(grid.size(4097).is_err());
+        assert_eq!(
+            grid.size(usize::MAX),
+            Err(TooLarge {
+                width: i64::MAX,
+                height: i64::MAX
+            })
+        );
+    }
+
+    #[test]
+    fn test_painting_robot() {
+        let robot = PaintingRobot::new(0);
+        let mut camera = robot.camera();
+        let mut painter = robot.painter();
+        for (colour, turn) in &[(1, 0), (0, 0), (1, 0), (1, 0), (0, 1), (1, 0), (1, 0)] {
+            painter.output(*colour).unwrap();
+            painter.output(*turn).unwrap();
+        }
+        assert_eq!(robot.painted(), 6);
+        let grid = robot.grid();
+        assert_eq!(grid.borrow().count(1), 4);
+        assert_eq!(camera.input().unwrap(), 0);
+    }
+}
@@ -0,0 +1,135 @@
+//! Just enough PNG to save a picture without pulling in an image crate: one IDAT chunk holding
+//! a zlib stream of stored (uncompressed) deflate blocks. The files are larger than they need
+//! to be, but every viewer opens them.
+use std::io::{self, Write};
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Largest payload of a stored deflate block.
+const BLOCK: usize = 0xffff;
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in bytes {
+        a = (a + u32::from(*byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn chunk<W: Write>(w: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    let mut body = kind.to_vec();
+    body.extend_from_slice(data);
+    w.write_all(&body)?;
+    w.write_all(&crc32(&body).to_be_bytes())
+}
+
+/// Writes an 8-bit RGB image. `rows` is every scanline already prefixed with its filter type.
+pub fn write<W: Write>(mut w: W, width: u32, height: u32, rows: &[u8]) -> io::Result<()> {
+    w.write_all(SIGNATURE)?;
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, RGB, deflate, no filtering method extensions, no interlacing
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    chunk(&mut w, b"IHDR", &header)?;
+
+    // zlib header for deflate with a 32K window and no compression
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = rows.chunks(BLOCK).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        zlib.push(blocks.peek().is_none() as u8);
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(rows).to_be_bytes());
+    chunk(&mut w, b"IDAT", &zlib)?;
+    chunk(&mut w, b"IEND", &[])
+}
+
+/// Reads back the scanlines of a file from [`write`], checking every checksum on the way.
+#[cfg(test)]
+pub fn decode(mut png: &[u8]) -> Option<Vec<u8>> {
+    fn be(bytes: &[u8]) -> u32 {
+        u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+    png = png.strip_prefix(SIGNATURE)?;
+    let mut zlib = Vec::new();
+    while png.len() >= 12 {
+        let len = be(png) as usize;
+        let body = png.get(4..8 + len)?;
+        if be(png.get(8 + len..12 + len)?) != crc32(body) {
+            return None;
+        }
+        if &body[..4] == b"IDAT" {
+            zlib.extend_from_slice(&body[4..]);
+        }
+        png = &png[12 + len..];
+    }
+    let mut stream = zlib.get(2..zlib.len().checked_sub(4)?)?;
+    let mut rows = Vec::new();
+    loop {
+        let (last, len, nlen) = (
+            stream[0],
+            u16::from_le_bytes([stream[1], stream[2]]),
+            u16::from_le_bytes([stream[3], stream[4]]),
+        );
+        if len != !nlen {
+            return None;
+        }
+        rows.extend_from_slice(stream.get(5..5 + len as usize)?);
+        stream = &stream[5 + len as usize..];
+        if last == 1 {
+            break;
+        }
+    }
+    if be(&zlib[zlib.len() - 4..]) != adler32(&rows) {
+        return None;
+    }
+    Some(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn test_blocks() {
+        let rows: Vec<u8> = (0..3 * BLOCK as u32 / 2).map(|i| (i % 251) as u8).collect();
+        let mut buf = Vec::new();
+        write(&mut buf, 1, 1, &rows).unwrap();
+        assert_eq!(decode(&buf), Some(rows));
+
+        let mut buf = Vec::new();
+        write(&mut buf, 0, 0, &[]).unwrap();
+        assert_eq!(decode(&buf), Some(Vec::new()));
+    }
+}
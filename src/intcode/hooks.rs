@@ -0,0 +1,165 @@
+//! Callbacks for everything a machine does, for tracers, profilers and debuggers.
+//!
+//! Register a [`Hook`] with [`IntcodeMachine::add_hook`](super::IntcodeMachine::add_hook).
+//! Every method has an empty default, so implement only what you need. Setting the `DEBUG`
+//! environment variable gives every new machine a [`Tracer`] on stdout.
+use super::Opcode;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+#[allow(unused_variables)]
+pub trait Hook {
+    /// `op` was decoded at `ip`.
+    fn on_fetch(&mut self, ip: usize, op: &Opcode) {}
+    /// A parameter read memory. Immediates and devices don't count.
+    fn on_read(&mut self, addr: usize, value: i32) {}
+    /// Memory at `addr` went from `old` to `new`. Device writes don't count.
+    fn on_write(&mut self, addr: usize, old: i32, new: i32) {}
+    /// The jump at `from` was taken.
+    fn on_jump(&mut self, from: usize, to: usize) {}
+    fn on_input(&mut self, value: i32) {}
+    fn on_output(&mut self, value: i32) {}
+    /// Called each time a halt is fetched.
+    fn on_halt(&mut self, ip: usize) {}
+}
+
+/// Writes a line for every event.
+pub struct Tracer {
+    out: Box<dyn Write>,
+}
+
+impl Tracer {
+    pub fn new(out: Box<dyn Write>) -> Tracer {
+        Tracer { out }
+    }
+
+    pub fn stdout() -> Tracer {
+        Tracer::new(Box::new(io::stdout()))
+    }
+}
+
+// a trace that can't be written isn't worth stopping the machine for
+impl Hook for Tracer {
+    fn on_fetch(&mut self, ip: usize, op: &Opcode) {
+        writeln!(self.out, "{:>5}: {}", ip, op).ok();
+    }
+
+    fn on_read(&mut self, addr: usize, value: i32) {
+        writeln!(self.out, "       [{}] = {}", addr, value).ok();
+    }
+
+    fn on_write(&mut self, addr: usize, old: i32, new: i32) {
+        writeln!(self.out, "       [{}] <- {} (was {})", addr, new, old).ok();
+    }
+
+    fn on_jump(&mut self, from: usize, to: usize) {
+        writeln!(self.out, "       jump {} -> {}", from, to).ok();
+    }
+
+    fn on_input(&mut self, value: i32) {
+        writeln!(self.out, "       input {}", value).ok();
+    }
+
+    fn on_output(&mut self, value: i32) {
+        writeln!(self.out, "       output {}", value).ok();
+    }
+
+    fn on_halt(&mut self, ip: usize) {
+        writeln!(self.out, "       halt at {}", ip).ok();
+    }
+}
+
+/// Counts how often each address gets executed. Hang on to [`counts`](Self::counts) to read
+/// them after the profiler is added to a machine.
+#[derive(Default)]
+pub struct Profiler {
+    counts: Rc<RefCell<BTreeMap<usize, usize>>>,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler::default()
+    }
+
+    pub fn counts(&self) -> Rc<RefCell<BTreeMap<usize, usize>>> {
+        self.counts.clone()
+    }
+}
+
+impl Hook for Profiler {
+    fn on_fetch(&mut self, ip: usize, _op: &Opcode) {
+        *self.counts.borrow_mut().entry(ip).or_insert(0) += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::IntcodeMachine;
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_tracer() {
+        // out(in + 1)
+        let data = &[3, 9, 1001, 9, 1, 9, 4, 9, 99, 0];
+        let log = Shared::default();
+        let mut machine = IntcodeMachine::new(data, Box::new(vec![41]), Box::new(Vec::new()));
+        machine.add_hook(Box::new(Tracer::new(Box::new(log.clone()))));
+        machine.run().unwrap();
+        let log = String::from_utf8(log.0.borrow().clone()).unwrap();
+        assert_eq!(
+            log.lines().collect::<Vec<_>>(),
+            vec![
+                "    0: input -> 9",
+                "       input 41",
+                "       [9] <- 41 (was 0)",
+                "    2: add (9) + 1 => 9",
+                "       [9] = 41",
+                "       [9] <- 42 (was 41)",
+                "    6: (9) -> output",
+                "       [9] = 42",
+                "       output 42",
+                "    8: halt",
+                "       halt at 8",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_profiler_and_jumps() {
+        // count mem[11] down from 3, then halt
+        let data = &[1001, 11, -1, 11, 1005, 11, 0, 99, 0, 0, 0, 3];
+        let profiler = Profiler::new();
+        let counts = profiler.counts();
+        let jumps = Rc::new(RefCell::new(Vec::new()));
+
+        struct Jumps(Rc<RefCell<Vec<(usize, usize)>>>);
+        impl Hook for Jumps {
+            fn on_jump(&mut self, from: usize, to: usize) {
+                self.0.borrow_mut().push((from, to));
+            }
+        }
+
+        let mut machine = IntcodeMachine::new(data, Box::new(Vec::new()), Box::new(Vec::new()));
+        machine.add_hook(Box::new(profiler));
+        machine.add_hook(Box::new(Jumps(jumps.clone())));
+        machine.run().unwrap();
+        let counts: Vec<(usize, usize)> = counts.borrow().iter().map(|(a, c)| (*a, *c)).collect();
+        assert_eq!(counts, vec![(0, 3), (4, 3), (7, 1)]);
+        assert_eq!(*jumps.borrow(), vec![(4, 0), (4, 0)]);
+    }
+}
@@ -0,0 +1,162 @@
+//! The day 2 interpreter, kept around as a reference to test the current one against.
+//!
+//! It only knows `Add`, `Mul` and `Halt`, every parameter is a position, and `execute` can't
+//! fail: an out of bounds address just panics. [`run`](IntcodeMachine::run) stops at the first
+//! error without saying what it was.
+use super::MachineError;
+use failure::Fallible;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+pub trait Instruction {
+    fn execute(self, cpu: &mut IntcodeMachine);
+}
+
+pub struct IntcodeMachine {
+    ip: usize,
+    halted: bool,
+    data: Vec<i32>,
+}
+
+impl IntcodeMachine {
+    pub fn new(data: &[i32]) -> IntcodeMachine {
+        IntcodeMachine {
+            ip: 0,
+            halted: false,
+            data: data.to_vec(),
+        }
+    }
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn memory(&self) -> &[i32] {
+        &self.data
+    }
+
+    pub fn set_cell(&mut self, pos: usize, val: i32) {
+        self.data[pos] = val;
+    }
+
+    pub fn value_at(&self, pos: usize) -> i32 {
+        self.data[pos]
+    }
+
+    fn halt(&mut self) {
+        self.halted = true
+    }
+
+    pub fn read_op(&mut self) -> Fallible<Opcode> {
+        if self.halted {
+            return Err(MachineError::Halted.into());
+        }
+        if self.ip >= self.data.len() {
+            return Err(MachineError::OutOfBounds(self.ip, self.data.len()).into());
+        }
+        let ip = self.ip;
+        let arg = |n: usize| -> Fallible<usize> {
+            self.data
+                .get(ip + n)
+                .map(|x| *x as usize)
+                .ok_or_else(|| MachineError::OutOfBounds(ip + n, self.data.len()).into())
+        };
+        let op = match self.data[ip] {
+            1 => Opcode::Add {
+                x: arg(1)?,
+                y: arg(2)?,
+                dest: arg(3)?,
+            },
+            2 => Opcode::Mul {
+                x: arg(1)?,
+                y: arg(2)?,
+                dest: arg(3)?,
+            },
+            99 => return Ok(Opcode::Halt),
+            x => return Err(MachineError::InvalidOpcode(x).into()),
+        };
+        self.ip += 4;
+        Ok(op)
+    }
+
+    pub fn run(&mut self) {
+        while let Ok(opcode) = self.read_op() {
+            opcode.execute(self);
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Opcode {
+    Add { x: usize, y: usize, dest: usize },
+    Mul { x: usize, y: usize, dest: usize },
+    Halt,
+}
+
+impl Display for Opcode {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        match self {
+            Opcode::Add { x, y, dest } => write!(w, "add %{} + %{} => %{}", x, y, dest),
+            Opcode::Mul { x, y, dest } => write!(w, "mul %{} * %{} => %{}", x, y, dest),
+            Opcode::Halt => write!(w, "halt"),
+        }
+    }
+}
+
+impl Instruction for Opcode {
+    fn execute(self, cpu: &mut IntcodeMachine) {
+        match self {
+            Opcode::Add { x, y, dest } => cpu.set_cell(dest, cpu.value_at(x) + cpu.value_at(y)),
+            Opcode::Mul { x, y, dest } => cpu.set_cell(dest, cpu.value_at(x) * cpu.value_at(y)),
+            Opcode::Halt => cpu.halt(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_single_add() {
+        let data = &[1, 0, 0, 0, 99];
+        let mut machine = IntcodeMachine::new(data);
+        let result = machine.read_op().unwrap();
+        let expected = Opcode::Add {
+            x: 0,
+            y: 0,
+            dest: 0,
+        };
+        assert_eq!(result, expected);
+        assert_eq!(machine.read_op().unwrap(), Opcode::Halt);
+    }
+
+    #[test]
+    fn test_read_single_mul() {
+        let data = &[2, 0, 0, 0, 99];
+        let mut machine = IntcodeMachine::new(data);
+        let result = machine.read_op().unwrap();
+        let expected = Opcode::Mul {
+            x: 0,
+            y: 0,
+            dest: 0,
+        };
+        assert_eq!(result, expected);
+        assert_eq!(machine.read_op().unwrap(), Opcode::Halt);
+    }
+
+    #[test]
+    fn test_single_add() {
+        let data = &[1, 5, 2, 3, 99, 0];
+        let mut machine = IntcodeMachine::new(data);
+        machine.run();
+        assert_eq!(machine.value_at(3), 2);
+    }
+
+    #[test]
+    fn test_single_mul() {
+        let data = &[2, 0, 0, 3, 99];
+        let mut machine = IntcodeMachine::new(data);
+        machine.run();
+        assert_eq!(machine.value_at(3), 4);
+    }
+}
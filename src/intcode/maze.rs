@@ -0,0 +1,252 @@
+//! Mapping out a maze by driving a droid program around it.
+//!
+//! The droid takes a direction (1 north, 2 south, 3 west, 4 east) and reports 0 if it hit a
+//! wall, 1 if it moved, or 2 if it moved onto the goal. Rather than walking the droid back
+//! and forth, [`Maze::explore`] snapshots the machine at every cell it reaches and forks a copy
+//! for each direction, so the search is a plain BFS outwards from the start.
+use super::grid::Grid;
+use super::{Instruction, IntcodeMachine, MachineError, Opcode, Snapshot};
+use failure::Fallible;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+pub type Position = (i32, i32);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    North,
+    South,
+    West,
+    East,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::South,
+        Direction::West,
+        Direction::East,
+    ];
+
+    /// What the droid expects as input.
+    pub fn command(self) -> i32 {
+        match self {
+            Direction::North => 1,
+            Direction::South => 2,
+            Direction::West => 3,
+            Direction::East => 4,
+        }
+    }
+
+    /// One step from `(x, y)`, with y growing southwards.
+    pub fn step(self, (x, y): Position) -> Position {
+        match self {
+            Direction::North => (x, y - 1),
+            Direction::South => (x, y + 1),
+            Direction::West => (x - 1, y),
+            Direction::East => (x + 1, y),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tile {
+    Wall,
+    Open,
+    Goal,
+}
+
+/// The droid answered with something other than 0, 1 or 2.
+#[derive(Debug)]
+pub struct UnknownStatus(pub i32);
+
+impl Display for UnknownStatus {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        write!(w, "droid reported status {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownStatus {}
+
+/// Sends `dir` to a fork of `snapshot` and runs it to its first output.
+fn probe(snapshot: &Snapshot, dir: Direction) -> Fallible<(i32, Snapshot)> {
+    let input = Box::new(vec![dir.command()]);
+    let mut machine = IntcodeMachine::restore(snapshot, input, Box::new(Vec::new()));
+    loop {
+        match machine.unpack_op()? {
+            Opcode::Halt => return Err(MachineError::Halted.into()),
+            Opcode::Output { x } => return Ok((machine.value_at(&x)?, machine.snapshot())),
+            op => op.execute(&mut machine)?,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Maze {
+    tiles: HashMap<Position, Tile>,
+    goal: Option<Position>,
+}
+
+impl Maze {
+    /// Explores every cell reachable from the droid's starting point, which becomes `(0, 0)`.
+    pub fn explore(program: &[i32]) -> Fallible<Maze> {
+        let start = IntcodeMachine::new(program, Box::new(Vec::new()), Box::new(Vec::new()));
+        let mut maze = Maze::default();
+        maze.tiles.insert((0, 0), Tile::Open);
+        let mut frontier = VecDeque::new();
+        frontier.push_back(((0, 0), start.snapshot()));
+        while let Some((pos, snapshot)) = frontier.pop_front() {
+            for dir in Direction::ALL.iter() {
+                let next = dir.step(pos);
+                if maze.tiles.contains_key(&next) {
+                    continue;
+                }
+                let (status, moved) = probe(&snapshot, *dir)?;
+                let tile = match status {
+                    0 => Tile::Wall,
+                    1 => Tile::Open,
+                    2 => Tile::Goal,
+                    x => return Err(UnknownStatus(x).into()),
+                };
+                maze.tiles.insert(next, tile);
+                if tile == Tile::Goal {
+                    maze.goal = Some(next);
+                }
+                if tile != Tile::Wall {
+                    frontier.push_back((next, moved));
+                }
+            }
+        }
+        Ok(maze)
+    }
+
+    pub fn tile(&self, pos: Position) -> Option<Tile> {
+        self.tiles.get(&pos).cloned()
+    }
+
+    fn is_open(&self, pos: Position) -> bool {
+        matches!(self.tile(pos), Some(Tile::Open) | Some(Tile::Goal))
+    }
+
+    pub fn goal(&self) -> Option<Position> {
+        self.goal
+    }
+
+    /// Steps from `from` to every reachable cell.
+    pub fn distances(&self, from: Position) -> HashMap<Position, usize> {
+        let mut seen = HashMap::new();
+        if !self.is_open(from) {
+            return seen;
+        }
+        seen.insert(from, 0);
+        let mut queue = VecDeque::new();
+        queue.push_back(from);
+        while let Some(pos) = queue.pop_front() {
+            let steps = seen[&pos];
+            for dir in Direction::ALL.iter() {
+                let next = dir.step(pos);
+                if self.is_open(next) && !seen.contains_key(&next) {
+                    seen.insert(next, steps + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        seen
+    }
+
+    pub fn shortest_path(&self, from: Position, to: Position) -> Option<usize> {
+        self.distances(from).get(&to).cloned()
+    }
+
+    /// How many steps it takes something spreading from `from` to fill the whole maze.
+    pub fn fill_time(&self, from: Position) -> Option<usize> {
+        self.distances(from).values().max().cloned()
+    }
+
+    /// Walls as 1 and the goal as 2, for [`Grid::render`].
+    pub fn to_grid(&self) -> Grid {
+        let mut grid = Grid::default();
+        for ((x, y), tile) in self.tiles.iter() {
+            let code = match tile {
+                Tile::Wall => 1,
+                Tile::Open => 0,
+                Tile::Goal => 2,
+            };
+            grid.set(*x, *y, code);
+        }
+        grid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::grid::default_palette;
+    use super::*;
+
+    /// A droid for a 6 wide maze, stored one cell per word (0 wall, 1 open, 2 goal) right after
+    /// the code. It starts at (1, 1) and looks up the cell it's moving into by patching the
+    /// address into a load instruction.
+    const DROID: &[i32] = &[
+        3, 99, 1001, 95, 0, 97, 1001, 96, 0, 98, // d = in, nx = x, ny = y
+        1008, 99, 1, 100, 1006, 100, 21, 1001, 98, -1, 98, // north
+        1008, 99, 2, 100, 1006, 100, 32, 1001, 98, 1, 98, // south
+        1008, 99, 3, 100, 1006, 100, 43, 1001, 97, -1, 97, // west
+        1008, 99, 4, 100, 1006, 100, 54, 1001, 97, 1, 97, // east
+        1002, 98, 6, 101, 1, 101, 97, 101, 1001, 101, 103, 101, // a = maze + ny * 6 + nx
+        1001, 101, 0, 71, 1001, 0, 0, 102, // c = mem[a]
+        1006, 102, 90, // wall?
+        1001, 97, 0, 95, 1001, 98, 0, 96, 4, 102, 1105, 1, 0, // move, out c
+        104, 0, 1105, 1, 0, // out 0
+        1, 1, 0, 0, 0, 0, 0, 0, // x, y, nx, ny, d, t, a, c
+    ];
+
+    fn droid(maze: &[&str]) -> Vec<i32> {
+        let cells = maze.iter().flat_map(|row| {
+            row.chars().map(|c| match c {
+                '#' => 0,
+                'G' => 2,
+                _ => 1,
+            })
+        });
+        DROID.iter().cloned().chain(cells).collect()
+    }
+
+    const MAZE: &[&str] = &[
+        "######", //
+        "#...##", //
+        "#.#.G#", //
+        "#...##", //
+        "######",
+    ];
+
+    #[test]
+    fn test_explore() {
+        let maze = Maze::explore(&droid(MAZE)).unwrap();
+        assert_eq!(maze.goal(), Some((3, 1)));
+        assert_eq!(maze.tile((1, 1)), Some(Tile::Wall));
+        assert_eq!(maze.tile((0, 2)), Some(Tile::Open));
+        // corners and walls behind walls are never probed
+        assert_eq!(
+            maze.to_grid().render(default_palette).unwrap(),
+            " ###\n#   #\n# # +#\n#   #\n ###\n"
+        );
+    }
+
+    #[test]
+    fn test_distances() {
+        let maze = Maze::explore(&droid(MAZE)).unwrap();
+        let goal = maze.goal().unwrap();
+        assert_eq!(maze.shortest_path((0, 0), goal), Some(4));
+        assert_eq!(maze.shortest_path((0, 0), (1, 1)), None);
+        assert_eq!(maze.fill_time(goal), Some(5));
+        assert_eq!(maze.distances((0, 0)).len(), 9);
+    }
+
+    #[test]
+    fn test_bad_status() {
+        let mut program = droid(MAZE);
+        program[103 + 6 + 2] = 7;
+        let err = Maze::explore(&program).unwrap_err();
+        assert_eq!(err.downcast_ref::<UnknownStatus>().map(|e| e.0), Some(7));
+    }
+}
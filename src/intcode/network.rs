@@ -0,0 +1,397 @@
+//! A network of Intcode machines talking in `(dest, x, y)` packets.
+//!
+//! Each machine is booted with its address as the first input and gets a crossbeam channel as
+//! its inbox. Reading from an empty inbox gives `-1` instead of blocking, so the whole network
+//! runs deterministically on one thread: every tick each machine runs up to its next I/O
+//! instruction, then the router delivers whatever was sent. Packets for [`NAT`] are held back
+//! and replayed to address 0 whenever the network goes idle.
+use super::{Input, Instruction, IntcodeMachine, MachineError, Opcode, Output};
+use crossbeam::channel::{unbounded, Receiver, Sender, TryRecvError};
+use failure::{Fallible, ResultExt};
+use std::cell::Cell;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::rc::Rc;
+
+pub const NAT: i32 = 255;
+
+/// Instructions a machine may run in one tick without touching I/O.
+const SLICE: usize = 10_000;
+
+/// Consecutive empty reads before a machine counts as idle.
+const IDLE_READS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Packet {
+    pub dest: i32,
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Sent {
+        from: i32,
+        packet: Packet,
+    },
+    /// Sent to an address nobody has.
+    Dropped {
+        from: i32,
+        packet: Packet,
+    },
+    Halted(i32),
+    /// Every machine is starved and nothing is in flight.
+    Idle,
+    /// The NAT replayed its last packet to address 0.
+    NatWake(Packet),
+}
+
+impl Display for Event {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        match self {
+            Event::Sent { from, packet } => {
+                write!(
+                    w,
+                    "{} -> {}: ({}, {})",
+                    from, packet.dest, packet.x, packet.y
+                )
+            }
+            Event::Dropped { from, packet } => write!(
+                w,
+                "{} -> {}: ({}, {}) dropped",
+                from, packet.dest, packet.x, packet.y
+            ),
+            Event::Halted(addr) => write!(w, "{} halted", addr),
+            Event::Idle => write!(w, "network idle"),
+            Event::NatWake(packet) => write!(w, "nat -> 0: ({}, {})", packet.x, packet.y),
+        }
+    }
+}
+
+/// Input side of a machine's network card.
+struct Nic {
+    address: Option<i32>,
+    inbox: Receiver<i32>,
+    empty_reads: Rc<Cell<usize>>,
+}
+
+impl Input for Nic {
+    fn input(&mut self) -> Fallible<i32> {
+        if let Some(address) = self.address.take() {
+            return Ok(address);
+        }
+        match self.inbox.try_recv() {
+            Ok(x) => {
+                self.empty_reads.set(0);
+                Ok(x)
+            }
+            Err(TryRecvError::Empty) => {
+                self.empty_reads.set(self.empty_reads.get() + 1);
+                Ok(-1)
+            }
+            Err(TryRecvError::Disconnected) => Err(MachineError::EOF.into()),
+        }
+    }
+}
+
+/// Output side of a machine's network card, assembling triples into packets.
+struct NicOutput {
+    address: i32,
+    pending: Vec<i32>,
+    outbox: Sender<(i32, Packet)>,
+    empty_reads: Rc<Cell<usize>>,
+}
+
+impl Output for NicOutput {
+    fn output(&mut self, what: i32) -> Fallible<()> {
+        self.empty_reads.set(0);
+        self.pending.push(what);
+        if let [dest, x, y] = self.pending[..] {
+            self.pending.clear();
+            self.outbox.send((self.address, Packet { dest, x, y }))?;
+        }
+        Ok(())
+    }
+
+    fn results(&self) -> Option<Vec<i32>> {
+        None
+    }
+}
+
+struct Node {
+    machine: IntcodeMachine,
+    inbox: Sender<i32>,
+    empty_reads: Rc<Cell<usize>>,
+    halted: bool,
+}
+
+pub struct Network {
+    nodes: Vec<Node>,
+    outbox: Receiver<(i32, Packet)>,
+    nat: Option<Packet>,
+    was_idle: bool,
+    log: Vec<Event>,
+}
+
+impl Network {
+    /// Boots `size` copies of `program` at addresses `0..size`.
+    pub fn boot(program: &[i32], size: usize) -> Network {
+        let (outbox_tx, outbox) = unbounded();
+        let nodes = (0..size as i32)
+            .map(|address| {
+                let (inbox, rx) = unbounded();
+                let empty_reads = Rc::new(Cell::new(0));
+                let input = Nic {
+                    address: Some(address),
+                    inbox: rx,
+                    empty_reads: empty_reads.clone(),
+                };
+                let output = NicOutput {
+                    address,
+                    pending: Vec::new(),
+                    outbox: outbox_tx.clone(),
+                    empty_reads: empty_reads.clone(),
+                };
+                Node {
+                    machine: IntcodeMachine::new(program, Box::new(input), Box::new(output)),
+                    inbox,
+                    empty_reads,
+                    halted: false,
+                }
+            })
+            .collect();
+        Network {
+            nodes,
+            outbox,
+            nat: None,
+            was_idle: false,
+            log: Vec::new(),
+        }
+    }
+
+    /// Everything that has happened so far, oldest first.
+    pub fn log(&self) -> &[Event] {
+        &self.log
+    }
+
+    /// The last packet the NAT received.
+    pub fn nat(&self) -> Option<Packet> {
+        self.nat
+    }
+
+    /// Whether the machine at `address` is halted or starved. There's no machine to be idle at
+    /// an address outside the network.
+    pub fn machine_idle(&self, address: usize) -> bool {
+        self.nodes.get(address).is_some_and(|node| {
+            node.halted || (node.inbox.is_empty() && node.empty_reads.get() >= IDLE_READS)
+        })
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.outbox.is_empty() && (0..self.nodes.len()).all(|a| self.machine_idle(a))
+    }
+
+    /// Hands `packet` to its destination, as if some machine had sent it.
+    pub fn send(&mut self, from: i32, packet: Packet) {
+        if packet.dest == NAT {
+            self.nat = Some(packet);
+            self.log.push(Event::Sent { from, packet });
+        } else if let Some(node) = self.nodes.get(packet.dest as usize) {
+            // unbounded, and we hold the receiver, so this can't fail
+            node.inbox.send(packet.x).unwrap();
+            node.inbox.send(packet.y).unwrap();
+            self.log.push(Event::Sent { from, packet });
+        } else {
+            self.log.push(Event::Dropped { from, packet });
+        }
+    }
+
+    /// Runs every machine up to its next I/O instruction and routes the results. Returns the
+    /// events from this tick.
+    pub fn tick(&mut self) -> Fallible<&[Event]> {
+        let start = self.log.len();
+        for (address, node) in self.nodes.iter_mut().enumerate() {
+            if node.halted {
+                continue;
+            }
+            for _ in 0..SLICE {
+                let op = node
+                    .machine
+                    .unpack_op()
+                    .with_context(|_| format!("machine {}", address))?;
+                match op {
+                    Opcode::Halt => {
+                        node.halted = true;
+                        self.log.push(Event::Halted(address as i32));
+                        break;
+                    }
+                    Opcode::Input { .. } | Opcode::Output { .. } => {
+                        op.execute(&mut node.machine)
+                            .with_context(|_| format!("machine {}", address))?;
+                        break;
+                    }
+                    op => op
+                        .execute(&mut node.machine)
+                        .with_context(|_| format!("machine {}", address))?,
+                }
+            }
+        }
+
+        let sent: Vec<(i32, Packet)> = self.outbox.try_iter().collect();
+        for (from, packet) in sent {
+            self.send(from, packet);
+        }
+
+        let idle = self.is_idle();
+        if idle && !self.was_idle {
+            self.log.push(Event::Idle);
+        }
+        self.was_idle = idle;
+        // with nobody at address 0 there's no one to wake, so the network stays idle
+        let wake = self.nodes.first().map(|node| node.empty_reads.clone());
+        if let (true, Some(packet), Some(empty_reads)) = (idle, self.nat, wake) {
+            let packet = Packet { dest: 0, ..packet };
+            self.send(NAT, packet);
+            self.log.push(Event::NatWake(packet));
+            empty_reads.set(0);
+            self.was_idle = false;
+        }
+        Ok(&self.log[start..])
+    }
+
+    /// Ticks until `stop` accepts an event, giving up after `max_ticks`.
+    pub fn run_until<F>(&mut self, max_ticks: usize, mut stop: F) -> Fallible<Option<Event>>
+    where
+        F: FnMut(&Event) -> bool,
+    {
+        for _ in 0..max_ticks {
+            if let Some(event) = self.tick()?.iter().find(|e| stop(e)) {
+                return Ok(Some(*event));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Address 0 sends (1, 7, 8) and then swallows input forever. Everyone else forwards each
+    /// packet to the NAT as (x, y + address).
+    fn ping() -> Vec<i32> {
+        let mut program = vec![
+            3, 100, // addr = input
+            1006, 100, 30, // if addr == 0 goto sender
+            3, 101, // x = input
+            1008, 101, -1, 102, // tmp = x == -1
+            1005, 102, 5, // if tmp goto 5
+            3, 103, // y = input
+            1, 103, 100, 103, // y += addr
+            104, 255, 4, 101, 4, 103, // send (255, x, y)
+            1105, 1, 5,  // goto 5
+            99, // unreachable
+            104, 1, 104, 7, 104, 8, // sender: send (1, 7, 8)
+            3, 101, 1105, 1, 36, // read forever
+        ];
+        program.resize(104, 0);
+        program
+    }
+
+    #[test]
+    fn test_routing_and_nat() {
+        let mut network = Network::boot(&ping(), 2);
+        let wake = network
+            .run_until(1000, |e| matches!(e, Event::NatWake(_)))
+            .unwrap();
+        let replayed = Packet {
+            dest: 0,
+            x: 7,
+            y: 9,
+        };
+        assert_eq!(wake, Some(Event::NatWake(replayed)));
+        let sent: Vec<Event> = network
+            .log()
+            .iter()
+            .filter(|e| matches!(e, Event::Sent { .. }))
+            .cloned()
+            .collect();
+        assert_eq!(
+            sent,
+            vec![
+                Event::Sent {
+                    from: 0,
+                    packet: Packet {
+                        dest: 1,
+                        x: 7,
+                        y: 8
+                    }
+                },
+                Event::Sent {
+                    from: 1,
+                    packet: Packet {
+                        dest: NAT,
+                        x: 7,
+                        y: 9
+                    }
+                },
+                Event::Sent {
+                    from: NAT,
+                    packet: replayed
+                },
+            ]
+        );
+        assert!(network.log().contains(&Event::Idle));
+        assert_eq!(format!("{}", sent[0]), "0 -> 1: (7, 8)");
+    }
+
+    #[test]
+    fn test_idle_detection() {
+        let mut network = Network::boot(&ping(), 3);
+        assert!(!network.is_idle());
+        network.tick().unwrap();
+        assert!(!network.machine_idle(1));
+        network
+            .run_until(1000, |e| *e == Event::Idle)
+            .unwrap()
+            .unwrap();
+        // the NAT wakes address 0 in the same tick the network goes idle
+        assert!(!network.machine_idle(0));
+        assert!(network.machine_idle(1) && network.machine_idle(2));
+        assert_eq!(network.nat().map(|p| p.y), Some(9));
+    }
+
+    #[test]
+    fn test_empty_network() {
+        let mut network = Network::boot(&ping(), 0);
+        assert!(!network.machine_idle(0));
+        assert!(network.is_idle());
+        let packet = Packet {
+            dest: NAT,
+            x: 1,
+            y: 2,
+        };
+        network.send(0, packet);
+        assert_eq!(network.tick().unwrap(), &[Event::Idle]);
+        assert_eq!(network.run_until(10, |_| true).unwrap(), None);
+        assert_eq!(network.nat(), Some(packet));
+    }
+
+    #[test]
+    fn test_unknown_address_is_dropped() {
+        let mut network = Network::boot(&ping(), 1);
+        let dropped = network
+            .run_until(100, |e| matches!(e, Event::Dropped { .. }))
+            .unwrap();
+        assert_eq!(
+            dropped,
+            Some(Event::Dropped {
+                from: 0,
+                packet: Packet {
+                    dest: 1,
+                    x: 7,
+                    y: 8
+                }
+            })
+        );
+    }
+}
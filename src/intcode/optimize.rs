@@ -0,0 +1,425 @@
+//! A conservative peephole pass over Intcode images.
+//!
+//! Writes always go to a fixed address, so once the [`ControlFlowGraph`] covers everything the
+//! program can run, we know every cell that can ever change. Cells nobody writes keep their
+//! load-time value: reads of them become immediates, and from there arithmetic and branches
+//! fold and jumps to jumps get threaded. Rewrites keep every instruction the same width, so no
+//! address moves.
+//!
+//! Anything that would make that reasoning unsound blocks the whole pass: jumps through memory,
+//! decoding errors, and writes landing on code. The image is assumed to run as is, so patch it
+//! (like day 2's noun and verb) before optimizing, and don't attach devices afterwards.
+use super::cfg::{ControlFlowGraph, Target};
+use super::{Opcode, Parameter};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// A read of a cell nobody writes became an immediate.
+    Propagate,
+    /// Arithmetic or a comparison on constants became `add value + 0`.
+    Fold,
+    AlwaysTaken,
+    /// Only noted, a jump that never jumps is as cheap as it gets.
+    NeverTaken,
+    /// A jump to an unconditional jump now goes straight to where that one ends up.
+    Thread,
+    /// Only noted, the store is to a cell nothing reads, but removing it would move code.
+    DeadStore,
+}
+
+impl Display for Kind {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        let name = match self {
+            Kind::Propagate => "propagated",
+            Kind::Fold => "folded",
+            Kind::AlwaysTaken => "always taken",
+            Kind::NeverTaken => "never taken",
+            Kind::Thread => "threaded",
+            Kind::DeadStore => "dead store",
+        };
+        write!(w, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Simplification {
+    pub addr: usize,
+    pub kind: Kind,
+    pub before: Opcode,
+    /// Same as `before` for the kinds that are only noted.
+    pub after: Opcode,
+}
+
+#[derive(Debug)]
+pub struct Report {
+    pub program: Vec<i32>,
+    pub simplifications: Vec<Simplification>,
+    /// Why nothing was rewritten. Empty if the pass ran.
+    pub blockers: Vec<String>,
+}
+
+impl Display for Report {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        for blocker in self.blockers.iter() {
+            writeln!(w, "not optimized: {}", blocker)?;
+        }
+        for s in self.simplifications.iter() {
+            if s.before == s.after {
+                writeln!(w, "{:>4}: {}  [{}]", s.addr, s.before, s.kind)?;
+            } else {
+                writeln!(
+                    w,
+                    "{:>4}: {}  =>  {}  [{}]",
+                    s.addr, s.before, s.after, s.kind
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn writes(op: &Opcode) -> Option<usize> {
+    match *op {
+        Opcode::Add { dest, .. }
+        | Opcode::Mul { dest, .. }
+        | Opcode::LessThan { dest, .. }
+        | Opcode::Equal { dest, .. } => Some(dest),
+        Opcode::Input { x } => Some(x),
+        _ => None,
+    }
+}
+
+fn reads(op: &Opcode) -> Vec<usize> {
+    let params: Vec<&Parameter> = match op {
+        Opcode::Add { x, y, .. }
+        | Opcode::Mul { x, y, .. }
+        | Opcode::LessThan { x, y, .. }
+        | Opcode::Equal { x, y, .. } => vec![x, y],
+        Opcode::Output { x } => vec![x],
+        Opcode::JumpIfTrue { x, dest } | Opcode::JumpIfFalse { x, dest } => vec![x, dest],
+        Opcode::Input { .. } | Opcode::Halt => vec![],
+    };
+    params
+        .into_iter()
+        .filter_map(|p| match p {
+            Parameter::Indirect(addr) => Some(*addr),
+            Parameter::Immediate(_) => None,
+        })
+        .collect()
+}
+
+/// Where an unconditional jump goes, if it is one.
+fn always_jumps_to(op: &Opcode) -> Option<usize> {
+    match *op {
+        Opcode::JumpIfTrue {
+            x: Parameter::Immediate(x),
+            dest: Parameter::Immediate(d),
+        } if x != 0 => Some(d as usize),
+        Opcode::JumpIfFalse {
+            x: Parameter::Immediate(0),
+            dest: Parameter::Immediate(d),
+        } => Some(d as usize),
+        _ => None,
+    }
+}
+
+/// Propagates constants into one instruction and folds whatever that settles.
+fn simplify(op: Opcode, constant: impl Fn(&Parameter) -> Parameter) -> (Opcode, Option<Kind>) {
+    use Parameter::Immediate;
+
+    let folded = |v: i32, dest| Opcode::Add {
+        x: Immediate(v),
+        y: Immediate(0),
+        dest,
+    };
+    let (after, kind) = match op {
+        Opcode::Add { x, y, dest } | Opcode::Mul { x, y, dest } => {
+            match (constant(&x), constant(&y)) {
+                (Immediate(a), Immediate(b)) => {
+                    let value = if let Opcode::Add { .. } = op {
+                        a.checked_add(b)
+                    } else {
+                        a.checked_mul(b)
+                    };
+                    match value {
+                        Some(v) => (folded(v, dest), Kind::Fold),
+                        // leave the overflow for the machine to trip over
+                        None => (op, Kind::Fold),
+                    }
+                }
+                (x, y) if matches!(op, Opcode::Add { .. }) => {
+                    (Opcode::Add { x, y, dest }, Kind::Propagate)
+                }
+                (x, y) => (Opcode::Mul { x, y, dest }, Kind::Propagate),
+            }
+        }
+        Opcode::LessThan { x, y, dest } | Opcode::Equal { x, y, dest } => {
+            match (constant(&x), constant(&y)) {
+                (Immediate(a), Immediate(b)) => {
+                    let value = if let Opcode::LessThan { .. } = op {
+                        a < b
+                    } else {
+                        a == b
+                    };
+                    (folded(value as i32, dest), Kind::Fold)
+                }
+                (x, y) if matches!(op, Opcode::LessThan { .. }) => {
+                    (Opcode::LessThan { x, y, dest }, Kind::Propagate)
+                }
+                (x, y) => (Opcode::Equal { x, y, dest }, Kind::Propagate),
+            }
+        }
+        Opcode::Output { x } => (Opcode::Output { x: constant(&x) }, Kind::Propagate),
+        Opcode::JumpIfTrue { x, dest } | Opcode::JumpIfFalse { x, dest } => {
+            let jump_if = matches!(op, Opcode::JumpIfTrue { .. });
+            let dest = constant(&dest);
+            match constant(&x) {
+                Immediate(v) if (v != 0) == jump_if => (
+                    Opcode::JumpIfTrue {
+                        x: Immediate(1),
+                        dest,
+                    },
+                    Kind::AlwaysTaken,
+                ),
+                Immediate(v) if jump_if => (
+                    Opcode::JumpIfTrue {
+                        x: Immediate(v),
+                        dest,
+                    },
+                    Kind::NeverTaken,
+                ),
+                Immediate(v) => (
+                    Opcode::JumpIfFalse {
+                        x: Immediate(v),
+                        dest,
+                    },
+                    Kind::NeverTaken,
+                ),
+                x if jump_if => (Opcode::JumpIfTrue { x, dest }, Kind::Propagate),
+                x => (Opcode::JumpIfFalse { x, dest }, Kind::Propagate),
+            }
+        }
+        Opcode::Input { .. } | Opcode::Halt => (op, Kind::Propagate),
+    };
+    if after == op && kind != Kind::NeverTaken {
+        (op, None)
+    } else {
+        (after, Some(kind))
+    }
+}
+
+pub fn optimize(data: &[i32]) -> Report {
+    let cfg = ControlFlowGraph::build(data);
+    let mut blockers = Vec::new();
+    let mut code: BTreeMap<usize, Opcode> = BTreeMap::new();
+    for block in cfg.blocks.values() {
+        if let Some(e) = &block.error {
+            blockers.push(format!("decoding failed at {}: {}", block.end, e));
+        }
+        if let Some((addr, _)) = block
+            .instructions
+            .last()
+            .filter(|_| block.successors.iter().any(|e| e.target == Target::Unknown))
+        {
+            blockers.push(format!("jump through memory at {}", addr));
+        }
+        code.extend(block.instructions.iter().cloned());
+    }
+
+    let code_cells: HashSet<usize> = code
+        .iter()
+        .flat_map(|(addr, op)| *addr..addr + op.len())
+        .collect();
+    let written: HashSet<usize> = code.values().filter_map(writes).collect();
+    let read: HashSet<usize> = code.values().flat_map(reads).collect();
+    for (addr, op) in code.iter() {
+        if let Some(dest) = writes(op).filter(|d| code_cells.contains(d)) {
+            blockers.push(format!("{} at {} writes to code at {}", op, addr, dest));
+        }
+    }
+
+    let mut report = Report {
+        program: data.to_vec(),
+        simplifications: Vec::new(),
+        blockers,
+    };
+    if !report.blockers.is_empty() {
+        return report;
+    }
+
+    // an instruction whose cells get read as data has to stay byte for byte the same
+    let rewritable = |addr: usize, op: &Opcode| !(addr..addr + op.len()).any(|c| read.contains(&c));
+    let constant = |p: &Parameter| match *p {
+        Parameter::Indirect(addr) if addr < data.len() && !written.contains(&addr) => {
+            Parameter::Immediate(data[addr])
+        }
+        p => p,
+    };
+
+    let mut rewritten = code.clone();
+    for (addr, op) in code.iter() {
+        if !rewritable(*addr, op) {
+            continue;
+        }
+        if let (after, Some(kind)) = simplify(*op, constant) {
+            rewritten.insert(*addr, after);
+            report.simplifications.push(Simplification {
+                addr: *addr,
+                kind,
+                before: *op,
+                after,
+            });
+        }
+    }
+
+    for (addr, op) in code.iter() {
+        let current = rewritten[addr];
+        let mut target = match current {
+            Opcode::JumpIfTrue {
+                dest: Parameter::Immediate(d),
+                ..
+            }
+            | Opcode::JumpIfFalse {
+                dest: Parameter::Immediate(d),
+                ..
+            } => d as usize,
+            _ => continue,
+        };
+        let mut seen = HashSet::new();
+        while let Some(next) = rewritten.get(&target).and_then(always_jumps_to) {
+            if !seen.insert(target) {
+                break;
+            }
+            target = next;
+        }
+        let dest = Parameter::Immediate(target as i32);
+        let after = match current {
+            Opcode::JumpIfTrue { x, .. } => Opcode::JumpIfTrue { x, dest },
+            Opcode::JumpIfFalse { x, .. } => Opcode::JumpIfFalse { x, dest },
+            _ => unreachable!(),
+        };
+        if after != current && rewritable(*addr, op) {
+            rewritten.insert(*addr, after);
+            report.simplifications.push(Simplification {
+                addr: *addr,
+                kind: Kind::Thread,
+                before: current,
+                after,
+            });
+        }
+    }
+
+    for (addr, op) in rewritten.iter() {
+        let dead = match op {
+            Opcode::Input { .. } => false,
+            op => writes(op).is_some_and(|d| !read.contains(&d)),
+        };
+        if dead {
+            report.simplifications.push(Simplification {
+                addr: *addr,
+                kind: Kind::DeadStore,
+                before: *op,
+                after: *op,
+            });
+        }
+        let cells = op.encode();
+        report.program[*addr..addr + cells.len()].copy_from_slice(&cells);
+    }
+    report.simplifications.sort_by_key(|s| s.addr);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::controller::{Ending, Scripted, Session};
+    use super::super::load_program;
+    use super::*;
+
+    fn run(data: &[i32], input: i32) -> Vec<i32> {
+        let mut session = Session::new(data, Scripted::new(vec![input]));
+        assert_eq!(session.run().unwrap(), Ending::Halted);
+        session.outputs()
+    }
+
+    const PROGRAM: &[i32] = &[
+        1, 20, 21, 22, // mem[22] = mem[20] + mem[21]
+        1005, 23, 10, // if mem[23] goto 10
+        4, 22, 99, // out mem[22], halt
+        1105, 1, 13, // goto 13
+        2, 22, 21, 24, // mem[24] = mem[22] * mem[21]
+        4, 22, 99, // out mem[22], halt
+        3, 4, 0, 1, 0,
+    ];
+
+    #[test]
+    fn test_simplifications() {
+        let report = optimize(PROGRAM);
+        assert!(report.blockers.is_empty());
+        let kinds: Vec<(usize, Kind)> = report
+            .simplifications
+            .iter()
+            .map(|s| (s.addr, s.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (0, Kind::Fold),
+                (4, Kind::AlwaysTaken),
+                (4, Kind::Thread),
+                (13, Kind::Propagate),
+                (13, Kind::DeadStore),
+            ]
+        );
+        assert_eq!(&report.program[..7], &[1101, 7, 0, 22, 1105, 1, 13]);
+        assert_eq!(report.program.len(), PROGRAM.len());
+        assert_eq!(run(&report.program, 0), run(PROGRAM, 0));
+        let listing = report.to_string();
+        assert!(listing.contains("   4: jmp-true 1 -> 10  =>  jmp-true 1 -> 13  [threaded]"));
+    }
+
+    #[test]
+    fn test_self_modification_blocks() {
+        // the add rewrites its own second operand
+        let data = &[1, 5, 6, 2, 4, 0, 99];
+        let report = optimize(data);
+        assert_eq!(
+            report.blockers,
+            vec!["add (5) + (6) => 2 at 0 writes to code at 2"]
+        );
+        assert!(report.simplifications.is_empty());
+        assert_eq!(&report.program[..], &data[..]);
+    }
+
+    #[test]
+    fn test_code_read_as_data_is_left_alone() {
+        // out mem[0], where mem[0] is the output instruction itself
+        let data = &[4, 0, 1, 7, 8, 9, 99, 2, 3, 0];
+        let report = optimize(data);
+        assert_eq!(report.program[0], 4);
+        assert_eq!(run(&report.program, 0), vec![4]);
+    }
+
+    #[test]
+    fn test_day5_side_by_side() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/inputs/day5.txt");
+        let data = load_program(path).unwrap();
+        // it patches its own code straight away, so the pass has to back off
+        let report = optimize(&data);
+        assert!(!report.blockers.is_empty());
+        assert_eq!(report.program, data);
+
+        // That patch adds the input to the opcode at 6. Do it ahead of time for input 1 and
+        // swap the add for one that only touches data, and the rest can be rewritten. Input 5
+        // takes the jump tests, which jump through memory, so it stays blocked either way.
+        let mut patched = data.clone();
+        patched[2..6].copy_from_slice(&[1001, 225, 0, 225]);
+        patched[6] = 1101;
+        let report = optimize(&patched);
+        assert!(report.blockers.is_empty(), "{:?}", report.blockers);
+        assert!(!report.simplifications.is_empty());
+        assert_ne!(report.program, patched);
+        assert_eq!(run(&report.program, 1), run(&data, 1));
+    }
+}
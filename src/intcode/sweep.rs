@@ -0,0 +1,336 @@
+//! Parallel parameter sweeps over patched copies of an Intcode image.
+//!
+//! Every combination of patched cell values (and swept inputs) gets its own machine, run on the
+//! rayon pool. The objective looks at how each run ended and either rejects it or gives it a
+//! score. Each run gets a step limit, so a program that loops for some values can't hold up the
+//! rest; those runs are reported separately.
+use super::IntcodeMachine;
+use rayon::prelude::*;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// How many instructions a run gets unless told otherwise.
+pub const STEP_LIMIT: usize = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// Stop at the first match, in sweep order.
+    First,
+    /// Every match, in sweep order.
+    All,
+    /// The highest scoring match. Ties go to the earliest.
+    Best,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Dimension {
+    Cell(usize),
+    Input,
+}
+
+/// How a single run finished.
+#[derive(Debug)]
+pub struct Outcome {
+    /// One value per dimension, in the order they were added.
+    pub values: Vec<i32>,
+    pub memory: Vec<i32>,
+    pub outputs: Vec<i32>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Hit {
+    pub values: Vec<i32>,
+    pub score: i64,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Swept {
+    pub hits: Vec<Hit>,
+    /// The values of every run that reached the step limit, in sweep order. With
+    /// [`Mode::First`] that's only the runs tried before the search stopped.
+    pub capped: Vec<Vec<i32>>,
+}
+
+/// The sweep has more runs than a `usize` can count.
+#[derive(Debug, PartialEq)]
+pub struct TooManyRuns;
+
+impl Display for TooManyRuns {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        write!(w, "too many runs to sweep")
+    }
+}
+
+impl std::error::Error for TooManyRuns {}
+
+enum Ran {
+    Finished(Outcome),
+    Failed,
+    Capped,
+}
+
+pub struct Sweep<'a> {
+    base: &'a [i32],
+    dimensions: Vec<(Dimension, RangeInclusive<i32>)>,
+    progress: Option<Box<dyn Fn(usize, usize) + Send + Sync + 'a>>,
+    step_limit: usize,
+}
+
+impl<'a> Sweep<'a> {
+    pub fn new(base: &'a [i32]) -> Sweep<'a> {
+        Sweep {
+            base,
+            dimensions: Vec::new(),
+            progress: None,
+            step_limit: STEP_LIMIT,
+        }
+    }
+
+    /// Tries every value in `values` at `addr`.
+    pub fn patch(mut self, addr: usize, values: RangeInclusive<i32>) -> Sweep<'a> {
+        self.dimensions.push((Dimension::Cell(addr), values));
+        self
+    }
+
+    /// Tries every value in `values` as the next input the program reads.
+    pub fn input(mut self, values: RangeInclusive<i32>) -> Sweep<'a> {
+        self.dimensions.push((Dimension::Input, values));
+        self
+    }
+
+    /// Called with `(runs finished, total runs)` roughly every percent of the sweep.
+    pub fn progress<F: Fn(usize, usize) + Send + Sync + 'a>(mut self, f: F) -> Sweep<'a> {
+        self.progress = Some(Box::new(f));
+        self
+    }
+
+    /// Stops each run after `steps` instructions.
+    pub fn step_limit(mut self, steps: usize) -> Sweep<'a> {
+        self.step_limit = steps;
+        self
+    }
+
+    pub fn total(&self) -> Result<usize, TooManyRuns> {
+        self.dimensions.iter().try_fold(1usize, |total, (_, r)| {
+            let len = (i64::from(*r.end()) - i64::from(*r.start()) + 1).max(0) as usize;
+            total.checked_mul(len).ok_or(TooManyRuns)
+        })
+    }
+
+    /// Mixed-radix decode of `index`, first dimension most significant.
+    fn values(&self, mut index: usize) -> Vec<i32> {
+        let mut values = vec![0; self.dimensions.len()];
+        for (i, (_, r)) in self.dimensions.iter().enumerate().rev() {
+            let len = (i64::from(*r.end()) - i64::from(*r.start()) + 1) as usize;
+            values[i] = r.start() + (index % len) as i32;
+            index /= len;
+        }
+        values
+    }
+
+    fn run_one(&self, values: Vec<i32>) -> Ran {
+        let mut inputs: Vec<i32> = self
+            .dimensions
+            .iter()
+            .zip(values.iter())
+            .filter(|((d, _), _)| *d == Dimension::Input)
+            .map(|(_, v)| *v)
+            .collect();
+        // Vec<i32> hands out inputs from the back
+        inputs.reverse();
+        let mut machine = IntcodeMachine::new(self.base, Box::new(inputs), Box::new(Vec::new()));
+        for ((d, _), v) in self.dimensions.iter().zip(values.iter()) {
+            if let Dimension::Cell(addr) = d {
+                if machine.set_cell(*addr, *v).is_err() {
+                    return Ran::Failed;
+                }
+            }
+        }
+        match machine.run_for(self.step_limit) {
+            Ok(true) => {}
+            Ok(false) => return Ran::Capped,
+            Err(_) => return Ran::Failed,
+        }
+        let memory = machine.memory().to_vec();
+        let outputs = match machine.take_output() {
+            Some(output) => output.results().unwrap_or_default(),
+            None => return Ran::Failed,
+        };
+        Ran::Finished(Outcome {
+            values,
+            memory,
+            outputs,
+        })
+    }
+
+    /// Runs the sweep. `objective` returns `None` to reject a run, or a score for it. Runs that
+    /// error out or reach the step limit never reach the objective.
+    pub fn run<F>(&self, mode: Mode, objective: F) -> Result<Swept, TooManyRuns>
+    where
+        F: Fn(&Outcome) -> Option<i64> + Send + Sync,
+    {
+        let total = self.total()?;
+        let done = AtomicUsize::new(0);
+        let every = (total / 100).max(1);
+        let capped = Mutex::new(Vec::new());
+        let attempt = |index: usize| -> Option<Hit> {
+            let values = self.values(index);
+            let hit = match self.run_one(values.clone()) {
+                Ran::Finished(outcome) => objective(&outcome).map(|score| Hit {
+                    values: outcome.values,
+                    score,
+                }),
+                Ran::Failed => None,
+                Ran::Capped => {
+                    capped.lock().unwrap().push((index, values));
+                    None
+                }
+            };
+            let finished = done.fetch_add(1, Ordering::Relaxed) + 1;
+            if let Some(progress) = &self.progress {
+                if finished.is_multiple_of(every) || finished == total {
+                    progress(finished, total);
+                }
+            }
+            hit
+        };
+
+        let hits = match mode {
+            Mode::First => (0..total)
+                .into_par_iter()
+                .find_map_first(attempt)
+                .into_iter()
+                .collect(),
+            Mode::All => (0..total).into_par_iter().filter_map(attempt).collect(),
+            Mode::Best => (0..total)
+                .into_par_iter()
+                .filter_map(attempt)
+                .reduce_with(|a, b| if b.score > a.score { b } else { a })
+                .into_iter()
+                .collect(),
+        };
+        let mut capped = capped.into_inner().unwrap();
+        capped.sort_unstable();
+        Ok(Swept {
+            hits,
+            capped: capped.into_iter().map(|(_, values)| values).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // mem[0] = mem[5] * mem[6]
+    const PRODUCT: &[i32] = &[2, 5, 6, 0, 99, 0, 0];
+
+    #[test]
+    fn test_values_are_mixed_radix() {
+        let sweep = Sweep::new(PRODUCT).patch(5, 0..=9).patch(6, -2..=2);
+        assert_eq!(sweep.total(), Ok(50));
+        assert_eq!(sweep.values(0), vec![0, -2]);
+        assert_eq!(sweep.values(7), vec![1, 0]);
+        assert_eq!(sweep.values(49), vec![9, 2]);
+    }
+
+    #[test]
+    fn test_first_match() {
+        let hits = Sweep::new(PRODUCT)
+            .patch(5, 0..=99)
+            .patch(6, 0..=99)
+            .run(Mode::First, |o| Some(0).filter(|_| o.memory[0] == 12))
+            .unwrap()
+            .hits;
+        assert_eq!(
+            hits,
+            vec![Hit {
+                values: vec![1, 12],
+                score: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn test_all_matches() {
+        let hits = Sweep::new(PRODUCT)
+            .patch(5, 0..=12)
+            .patch(6, 0..=12)
+            .run(Mode::All, |o| Some(0).filter(|_| o.memory[0] == 12))
+            .unwrap()
+            .hits;
+        let values: Vec<Vec<i32>> = hits.into_iter().map(|h| h.values).collect();
+        assert_eq!(
+            values,
+            vec![
+                vec![1, 12],
+                vec![2, 6],
+                vec![3, 4],
+                vec![4, 3],
+                vec![6, 2],
+                vec![12, 1]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_best_over_outputs() {
+        // output(input * mem[9])
+        let data = &[3, 10, 2, 10, 9, 10, 4, 10, 99, 0, 0];
+        let hits = Sweep::new(data)
+            .input(-3..=3)
+            .patch(9, -2..=2)
+            .run(Mode::Best, |o| o.outputs.first().map(|x| i64::from(*x)))
+            .unwrap()
+            .hits;
+        assert_eq!(
+            hits,
+            vec![Hit {
+                values: vec![-3, -2],
+                score: 6
+            }]
+        );
+    }
+
+    #[test]
+    fn test_progress_reaches_total() {
+        let seen = AtomicUsize::new(0);
+        Sweep::new(PRODUCT)
+            .patch(5, 0..=9)
+            .progress(|done, total| {
+                assert!(done <= total);
+                seen.fetch_max(done, Ordering::Relaxed);
+            })
+            .run(Mode::All, |_| Some(0))
+            .unwrap();
+        assert_eq!(seen.load(Ordering::Relaxed), 10);
+    }
+
+    #[test]
+    fn test_limits() {
+        let wide = Sweep::new(PRODUCT)
+            .patch(5, i32::MIN..=i32::MAX)
+            .patch(6, i32::MIN..=i32::MAX)
+            .patch(0, i32::MIN..=i32::MAX);
+        assert_eq!(wide.total(), Err(TooManyRuns));
+        assert_eq!(wide.run(Mode::All, |_| Some(0)), Err(TooManyRuns));
+
+        // jumps back to itself unless mem[6] is 0: 1005 6 0 (jump-if-true) then output mem[6]
+        let data = &[1005, 6, 0, 4, 6, 99, 0];
+        let swept = Sweep::new(data)
+            .patch(6, -2..=2)
+            .step_limit(1000)
+            .run(Mode::All, |o| Some(i64::from(o.outputs[0])))
+            .unwrap();
+        assert_eq!(
+            swept.hits,
+            vec![Hit {
+                values: vec![0],
+                score: 0
+            }]
+        );
+        assert_eq!(swept.capped, vec![vec![-2], vec![-1], vec![1], vec![2]]);
+    }
+}
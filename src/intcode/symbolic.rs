@@ -0,0 +1,620 @@
+//! Symbolic execution for (mostly) straight-line Intcode programs.
+//!
+//! Chosen cells and inputs start out as variables, and `Add`/`Mul`/`LessThan`/`Equal` build
+//! expressions over them instead of numbers. Once the program halts, [`solve`] works backwards
+//! from the expression in the observed cell to the variable values that produce a target. Any
+//! time the machine would need to know a variable's value (an opcode, a write address or a
+//! branch condition), symbolic execution gives up and [`solve`] sweeps every assignment instead.
+use super::sweep::{Mode, Outcome, Sweep, TooManyRuns};
+use itertools::Itertools;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::ops::RangeInclusive;
+
+const STEP_LIMIT: usize = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(pub usize);
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "v{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Const(i32),
+    Var(Symbol),
+    /// A read through a symbolic pointer. Harmless unless it reaches the result.
+    Opaque,
+    Add(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    LessThan(Box<Expr>, Box<Expr>),
+    Equal(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn add(x: Expr, y: Expr) -> Expr {
+        match (x, y) {
+            // an overflowing sum stays a node, and `eval` turns it into `None`
+            (Expr::Const(a), Expr::Const(b)) if a.checked_add(b).is_some() => Expr::Const(a + b),
+            (Expr::Const(0), e) | (e, Expr::Const(0)) => e,
+            (x, y) => Expr::Add(Box::new(x), Box::new(y)),
+        }
+    }
+
+    fn mul(x: Expr, y: Expr) -> Expr {
+        match (x, y) {
+            (Expr::Const(a), Expr::Const(b)) if a.checked_mul(b).is_some() => Expr::Const(a * b),
+            (Expr::Const(0), _) | (_, Expr::Const(0)) => Expr::Const(0),
+            (Expr::Const(1), e) | (e, Expr::Const(1)) => e,
+            (x, y) => Expr::Mul(Box::new(x), Box::new(y)),
+        }
+    }
+
+    fn less_than(x: Expr, y: Expr) -> Expr {
+        match (x, y) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const((a < b) as i32),
+            (x, y) => Expr::LessThan(Box::new(x), Box::new(y)),
+        }
+    }
+
+    fn equal(x: Expr, y: Expr) -> Expr {
+        match (x, y) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const((a == b) as i32),
+            (x, y) => Expr::Equal(Box::new(x), Box::new(y)),
+        }
+    }
+
+    pub fn as_const(&self) -> Option<i32> {
+        match self {
+            Expr::Const(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// Whether any part of the expression came from a symbolic pointer.
+    pub fn is_opaque(&self) -> bool {
+        match self {
+            Expr::Opaque => true,
+            Expr::Const(_) | Expr::Var(_) => false,
+            Expr::Add(x, y) | Expr::Mul(x, y) | Expr::LessThan(x, y) | Expr::Equal(x, y) => {
+                x.is_opaque() || y.is_opaque()
+            }
+        }
+    }
+
+    /// Evaluates the expression with `Symbol(i)` bound to `values[i]`.
+    pub fn eval(&self, values: &[i32]) -> Option<i32> {
+        Some(match self {
+            Expr::Const(x) => *x,
+            Expr::Var(s) => *values.get(s.0)?,
+            Expr::Opaque => return None,
+            Expr::Add(x, y) => x.eval(values)?.checked_add(y.eval(values)?)?,
+            Expr::Mul(x, y) => x.eval(values)?.checked_mul(y.eval(values)?)?,
+            Expr::LessThan(x, y) => (x.eval(values)? < y.eval(values)?) as i32,
+            Expr::Equal(x, y) => (x.eval(values)? == y.eval(values)?) as i32,
+        })
+    }
+
+    /// Rewrites the expression as `sum(coefficient * symbol) + constant`, if it is affine and
+    /// that fits in an `i64`.
+    pub fn affine(&self) -> Option<(BTreeMap<Symbol, i64>, i64)> {
+        match self {
+            Expr::Const(x) => Some((BTreeMap::new(), i64::from(*x))),
+            Expr::Var(s) => Some((vec![(*s, 1)].into_iter().collect(), 0)),
+            Expr::Add(x, y) => {
+                let (mut terms, k1) = x.affine()?;
+                let (other, k2) = y.affine()?;
+                for (s, c) in other {
+                    let term = terms.entry(s).or_insert(0);
+                    *term = term.checked_add(c)?;
+                }
+                Some((terms, k1.checked_add(k2)?))
+            }
+            Expr::Mul(x, y) => {
+                let (tx, kx) = x.affine()?;
+                let (ty, ky) = y.affine()?;
+                let (terms, k, scale) = match (tx.is_empty(), ty.is_empty()) {
+                    (true, _) => (ty, ky, kx),
+                    (_, true) => (tx, kx, ky),
+                    _ => return None,
+                };
+                let terms = terms
+                    .into_iter()
+                    .map(|(s, c)| Some((s, c.checked_mul(scale)?)))
+                    .collect::<Option<_>>()?;
+                Some((terms, k.checked_mul(scale)?))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Expr::Const(x) => write!(f, "{}", x),
+            Expr::Var(s) => write!(f, "{}", s),
+            Expr::Opaque => write!(f, "?"),
+            Expr::Add(x, y) => write!(f, "({} + {})", x, y),
+            Expr::Mul(x, y) => write!(f, "({} * {})", x, y),
+            Expr::LessThan(x, y) => write!(f, "({} < {})", x, y),
+            Expr::Equal(x, y) => write!(f, "({} == {})", x, y),
+        }
+    }
+}
+
+/// Symbolic execution couldn't continue without knowing a variable's value.
+#[derive(Debug)]
+pub struct Undecided {
+    pub ip: usize,
+    pub reason: &'static str,
+}
+
+impl Display for Undecided {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        write!(w, "undecided at {}: {}", self.ip, self.reason)
+    }
+}
+
+impl std::error::Error for Undecided {}
+
+pub struct SymbolicMachine {
+    ip: usize,
+    memory: Vec<Expr>,
+    inputs: VecDeque<Expr>,
+    outputs: Vec<Expr>,
+    symbols: usize,
+}
+
+impl SymbolicMachine {
+    pub fn new(data: &[i32]) -> SymbolicMachine {
+        SymbolicMachine {
+            ip: 0,
+            memory: data.iter().cloned().map(Expr::Const).collect(),
+            inputs: VecDeque::new(),
+            outputs: Vec::new(),
+            symbols: 0,
+        }
+    }
+
+    fn fresh(&mut self) -> Symbol {
+        self.symbols += 1;
+        Symbol(self.symbols - 1)
+    }
+
+    /// Replaces the contents of `addr` with a new variable.
+    pub fn symbolic_cell(&mut self, addr: usize) -> Result<Symbol, Undecided> {
+        if addr >= self.memory.len() {
+            return Err(self.undecided("address out of bounds"));
+        }
+        let s = self.fresh();
+        self.memory[addr] = Expr::Var(s);
+        Ok(s)
+    }
+
+    /// Queues a new variable to be read by the next unserved `Input`.
+    pub fn symbolic_input(&mut self) -> Symbol {
+        let s = self.fresh();
+        self.inputs.push_back(Expr::Var(s));
+        s
+    }
+
+    pub fn push_input(&mut self, value: i32) {
+        self.inputs.push_back(Expr::Const(value));
+    }
+
+    pub fn memory(&self) -> &[Expr] {
+        &self.memory
+    }
+
+    pub fn outputs(&self) -> &[Expr] {
+        &self.outputs
+    }
+
+    fn undecided(&self, reason: &'static str) -> Undecided {
+        Undecided {
+            ip: self.ip,
+            reason,
+        }
+    }
+
+    fn cell(&self, addr: usize) -> Result<&Expr, Undecided> {
+        self.memory
+            .get(addr)
+            .ok_or_else(|| self.undecided("address out of bounds"))
+    }
+
+    fn address(&self, n: usize) -> Result<usize, Undecided> {
+        match self.cell(self.ip + n)? {
+            Expr::Const(x) if *x >= 0 => Ok(*x as usize),
+            Expr::Const(_) => Err(self.undecided("negative address")),
+            _ => Err(self.undecided("symbolic address")),
+        }
+    }
+
+    /// Fetches operand `n` of the current instruction in the given mode.
+    fn param(&self, n: usize, mode: i32) -> Result<Expr, Undecided> {
+        match mode {
+            0 => match self.cell(self.ip + n)? {
+                Expr::Const(x) if *x >= 0 => Ok(self.cell(*x as usize)?.clone()),
+                Expr::Const(_) => Err(self.undecided("negative address")),
+                _ => Ok(Expr::Opaque),
+            },
+            1 => Ok(self.cell(self.ip + n)?.clone()),
+            _ => Err(self.undecided("invalid parameter mode")),
+        }
+    }
+
+    fn set(&mut self, addr: usize, value: Expr) -> Result<(), Undecided> {
+        match self.memory.get_mut(addr) {
+            Some(cell) => {
+                *cell = value;
+                Ok(())
+            }
+            None => Err(self.undecided("address out of bounds")),
+        }
+    }
+
+    fn jump(&mut self, cond: Expr, dest: Expr, if_true: bool) -> Result<(), Undecided> {
+        let cond = cond
+            .as_const()
+            .ok_or_else(|| self.undecided("symbolic branch condition"))?;
+        if (cond != 0) == if_true {
+            match dest {
+                Expr::Const(d) if d >= 0 => self.ip = d as usize,
+                _ => return Err(self.undecided("symbolic jump target")),
+            }
+        } else {
+            self.ip += 3;
+        }
+        Ok(())
+    }
+
+    pub fn run(&mut self) -> Result<(), Undecided> {
+        for _ in 0..STEP_LIMIT {
+            let op = self
+                .cell(self.ip)?
+                .as_const()
+                .ok_or_else(|| self.undecided("symbolic opcode"))?;
+            let (m1, m2) = ((op / 100) % 10, (op / 1000) % 10);
+            match op % 100 {
+                1 | 2 | 7 | 8 => {
+                    let x = self.param(1, m1)?;
+                    let y = self.param(2, m2)?;
+                    let dest = self.address(3)?;
+                    let value = match op % 100 {
+                        1 => Expr::add(x, y),
+                        2 => Expr::mul(x, y),
+                        7 => Expr::less_than(x, y),
+                        _ => Expr::equal(x, y),
+                    };
+                    self.set(dest, value)?;
+                    self.ip += 4;
+                }
+                3 => {
+                    let dest = self.address(1)?;
+                    let value = self
+                        .inputs
+                        .pop_front()
+                        .ok_or_else(|| self.undecided("out of input"))?;
+                    self.set(dest, value)?;
+                    self.ip += 2;
+                }
+                4 => {
+                    let x = self.param(1, m1)?;
+                    self.outputs.push(x);
+                    self.ip += 2;
+                }
+                5 | 6 => {
+                    let cond = self.param(1, m1)?;
+                    let dest = self.param(2, m2)?;
+                    self.jump(cond, dest, op % 100 == 5)?;
+                }
+                99 => return Ok(()),
+                _ => return Err(self.undecided("invalid opcode")),
+            }
+        }
+        Err(self.undecided("step limit reached"))
+    }
+}
+
+/// Something `solve` is allowed to pick a value for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unknown {
+    Cell(usize),
+    /// The next value read by an `Input`, in the order the unknowns are listed.
+    Input,
+}
+
+/// Where `solve` looks for the target value once the program halts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Observe {
+    Cell(usize),
+    Output(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strategy {
+    /// The result was affine in the unknowns and solved directly.
+    Linear,
+    /// The result was a symbolic expression, searched by evaluating it.
+    Search,
+    /// Symbolic execution gave up, so every assignment was run on a real machine.
+    Enumeration,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Solution {
+    /// One value per unknown, in the order they were given.
+    pub values: Vec<i32>,
+    pub strategy: Strategy,
+}
+
+/// Why [`solve`] came back without values.
+#[derive(Debug, PartialEq)]
+pub enum Unsolved {
+    /// No assignment leaves the target.
+    NoSolution,
+    /// There are too many assignments to run them all.
+    TooManyRuns,
+    /// None of the assignments that finished leave the target, but these ones reached the
+    /// sweep's step limit.
+    Capped(Vec<Vec<i32>>),
+}
+
+impl Display for Unsolved {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        match self {
+            Unsolved::NoSolution => write!(w, "no values give the target"),
+            Unsolved::TooManyRuns => write!(w, "{}", TooManyRuns),
+            Unsolved::Capped(runs) => write!(
+                w,
+                "no values give the target, but {} runs reached the step limit",
+                runs.len()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Unsolved {}
+
+fn solve_linear(
+    terms: &BTreeMap<Symbol, i64>,
+    k: i64,
+    domains: &[RangeInclusive<i32>],
+    target: i32,
+) -> Option<Vec<i32>> {
+    // enumerate every unknown except the one we solve for
+    let pivot = terms
+        .iter()
+        .rev()
+        .find(|(_, c)| **c != 0)
+        .map(|(s, c)| (s.0, *c));
+    let free: Vec<usize> = (0..domains.len())
+        .filter(|i| Some(*i) != pivot.map(|p| p.0))
+        .collect();
+    let assignments = free
+        .iter()
+        .map(|i| domains[*i].clone())
+        .multi_cartesian_product();
+    let check = |chosen: Vec<i32>| -> Option<Vec<i32>> {
+        let mut values: Vec<i32> = domains.iter().map(|d| *d.start()).collect();
+        for (i, v) in free.iter().zip(chosen) {
+            values[*i] = v;
+        }
+        let partial = terms
+            .iter()
+            .filter(|(s, _)| Some(s.0) != pivot.map(|p| p.0))
+            .try_fold(0i64, |sum, (s, c)| {
+                sum.checked_add(c.checked_mul(i64::from(values[s.0]))?)
+            })?;
+        let rest = i64::from(target).checked_sub(k)?.checked_sub(partial)?;
+        match pivot {
+            None if rest == 0 => Some(values),
+            None => None,
+            Some((p, c)) if rest % c == 0 => {
+                let v = rest / c;
+                if v >= i64::from(*domains[p].start()) && v <= i64::from(*domains[p].end()) {
+                    values[p] = v as i32;
+                    Some(values)
+                } else {
+                    None
+                }
+            }
+            Some(_) => None,
+        }
+    };
+    if free.is_empty() {
+        check(Vec::new())
+    } else {
+        assignments.filter_map(check).next()
+    }
+}
+
+/// Finds values for `unknowns`, each drawn from its range, that leave `target` in the observed
+/// location once `data` halts. There are none when an unknown cell is outside the program.
+pub fn solve(
+    data: &[i32],
+    unknowns: &[(Unknown, RangeInclusive<i32>)],
+    observe: Observe,
+    target: i32,
+) -> Result<Solution, Unsolved> {
+    let mut machine = SymbolicMachine::new(data);
+    for (unknown, _) in unknowns.iter() {
+        match unknown {
+            // a real machine can't patch it either
+            Unknown::Cell(addr) => machine
+                .symbolic_cell(*addr)
+                .map_err(|_| Unsolved::NoSolution)?,
+            Unknown::Input => machine.symbolic_input(),
+        };
+    }
+    let domains: Vec<RangeInclusive<i32>> = unknowns.iter().map(|(_, d)| d.clone()).collect();
+
+    let result = machine.run().ok().and_then(|_| match observe {
+        Observe::Cell(addr) => machine.memory().get(addr).cloned(),
+        Observe::Output(n) => machine.outputs().get(n).cloned(),
+    });
+    match result {
+        None => {}
+        Some(expr) => {
+            // the affine form is exact, but the machine works in i32 and stops on an overflow
+            // the i64 solution wouldn't see, so check it the way the machine would
+            let linear = expr
+                .affine()
+                .map(|(terms, k)| solve_linear(&terms, k, &domains, target));
+            match linear {
+                Some(Some(values)) if expr.eval(&values) == Some(target) => {
+                    return Ok(Solution {
+                        values,
+                        strategy: Strategy::Linear,
+                    });
+                }
+                Some(None) => return Err(Unsolved::NoSolution),
+                _ => {}
+            }
+            if !expr.is_opaque() {
+                return domains
+                    .iter()
+                    .cloned()
+                    .multi_cartesian_product()
+                    .find(|values| expr.eval(values) == Some(target))
+                    .map(|values| Solution {
+                        values,
+                        strategy: Strategy::Search,
+                    })
+                    .ok_or(Unsolved::NoSolution);
+            }
+        }
+    }
+
+    let sweep = unknowns
+        .iter()
+        .fold(Sweep::new(data), |sweep, (unknown, domain)| match unknown {
+            Unknown::Cell(addr) => sweep.patch(*addr, domain.clone()),
+            Unknown::Input => sweep.input(domain.clone()),
+        });
+    let observed = |outcome: &Outcome| match observe {
+        Observe::Cell(addr) => outcome.memory.get(addr).cloned(),
+        Observe::Output(n) => outcome.outputs.get(n).cloned(),
+    };
+    let mut swept = sweep
+        .run(Mode::First, |outcome| {
+            Some(0).filter(|_| observed(outcome) == Some(target))
+        })
+        .map_err(|_| Unsolved::TooManyRuns)?;
+    match swept.hits.pop() {
+        Some(hit) => Ok(Solution {
+            values: hit.values,
+            strategy: Strategy::Enumeration,
+        }),
+        None if swept.capped.is_empty() => Err(Unsolved::NoSolution),
+        None => Err(Unsolved::Capped(swept.capped)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_affine_result() {
+        let data = &[1, 9, 10, 0, 2, 0, 11, 0, 99, 0, 0, 3];
+        let mut machine = SymbolicMachine::new(data);
+        machine.symbolic_cell(9).unwrap();
+        machine.symbolic_cell(10).unwrap();
+        machine.run().unwrap();
+        assert_eq!(format!("{}", machine.memory()[0]), "((v0 + v1) * 3)");
+
+        let unknowns = &[(Unknown::Cell(9), 0..=99), (Unknown::Cell(10), 0..=99)];
+        let solution = solve(data, unknowns, Observe::Cell(0), 30).unwrap();
+        assert_eq!(solution.strategy, Strategy::Linear);
+        assert_eq!(solution.values, vec![0, 10]);
+        assert_eq!(
+            solve(data, unknowns, Observe::Cell(0), 31),
+            Err(Unsolved::NoSolution)
+        );
+    }
+
+    #[test]
+    fn test_symbolic_pointer_is_harmless_when_overwritten() {
+        // like day 2: the first instruction reads through the noun and verb
+        let data = &[1, 0, 0, 3, 1, 1, 2, 0, 99];
+        let unknowns = &[(Unknown::Cell(1), 0..=99), (Unknown::Cell(2), 0..=99)];
+        let solution = solve(data, unknowns, Observe::Cell(0), 5).unwrap();
+        assert_eq!(solution.strategy, Strategy::Linear);
+        assert_eq!(solution.values, vec![0, 5]);
+    }
+
+    #[test]
+    fn test_non_linear_search() {
+        let data = &[2, 5, 6, 0, 99, 0, 0];
+        let unknowns = &[(Unknown::Cell(5), 0..=20), (Unknown::Cell(6), 0..=20)];
+        let solution = solve(data, unknowns, Observe::Cell(0), 12).unwrap();
+        assert_eq!(solution.strategy, Strategy::Search);
+        assert_eq!(solution.values, vec![1, 12]);
+    }
+
+    #[test]
+    fn test_branch_falls_back_to_enumeration() {
+        let data = &[3, 11, 1005, 11, 8, 104, 1, 99, 104, 2, 99, 0];
+        let unknowns = &[(Unknown::Input, 0..=5)];
+        let solution = solve(data, unknowns, Observe::Output(0), 2).unwrap();
+        assert_eq!(solution.strategy, Strategy::Enumeration);
+        assert_eq!(solution.values, vec![1]);
+    }
+
+    #[test]
+    fn test_enumeration_reports_capped_runs() {
+        // loops forever when the input is 3, and outputs it otherwise
+        let data = &[3, 13, 1008, 13, 3, 14, 1005, 14, 6, 4, 13, 99, 0, 0, 0];
+        let unknowns = &[(Unknown::Input, 0..=5)];
+        let solution = solve(data, unknowns, Observe::Output(0), 4).unwrap();
+        assert_eq!(solution.values, vec![4]);
+        assert_eq!(
+            solve(data, unknowns, Observe::Output(0), 7),
+            Err(Unsolved::Capped(vec![vec![3]]))
+        );
+    }
+
+    #[test]
+    fn test_linear_answer_is_checked() {
+        // v0 + v1 - 1, but only once the first addition doesn't overflow
+        let mut data = vec![1, 20, 21, 22, 1, 22, 23, 22, 1, 22, 24, 0, 99];
+        data.resize(25, 0);
+        data[20] = i32::MIN;
+        data[23] = i32::MAX;
+        let unknowns = &[(Unknown::Cell(21), -1..=1), (Unknown::Cell(24), 0..=5)];
+        let solution = solve(&data, unknowns, Observe::Cell(0), 0).unwrap();
+        assert_eq!(solution.strategy, Strategy::Search);
+        assert_eq!(solution.values, vec![0, 1]);
+
+        let big = Expr::mul(Expr::Var(Symbol(0)), Expr::Const(i32::MAX));
+        let bigger = Expr::mul(big, Expr::Const(i32::MAX));
+        assert!(bigger.affine().is_some());
+        assert_eq!(Expr::mul(bigger, Expr::Const(i32::MAX)).affine(), None);
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let data = &[1, 5, 6, 0, 99, 0, 0];
+        let mut machine = SymbolicMachine::new(data);
+        assert!(machine.symbolic_cell(7).is_err());
+        let unknowns = &[(Unknown::Cell(7), 0..=9)];
+        assert_eq!(
+            solve(data, unknowns, Observe::Cell(0), 1),
+            Err(Unsolved::NoSolution)
+        );
+
+        // constants that overflow aren't folded, and evaluate to nothing
+        let sum = Expr::add(Expr::Const(i32::MAX), Expr::Const(1));
+        assert_eq!(sum.to_string(), "(2147483647 + 1)");
+        assert_eq!(sum.eval(&[]), None);
+        let product = Expr::mul(Expr::Const(1 << 16), Expr::Const(1 << 16));
+        assert_eq!(product.as_const(), None);
+        assert_eq!(Expr::mul(Expr::Const(6), Expr::Const(7)), Expr::Const(42));
+    }
+}
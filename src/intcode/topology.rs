@@ -0,0 +1,370 @@
+//! Wiring several machines together from a small description file.
+//!
+//! One directive per line, `#` starts a comment:
+//!
+//! ```text
+//! machine a day7.txt $0 0    # name, program, then initial inputs
+//! machine b day7.txt $1
+//! link a b                   # everything a outputs goes to b
+//! sink b                     # report what b outputs
+//! permute 0 1 2 3 4          # try $0, $1, .. as every ordering of these
+//! ```
+//!
+//! A machine linked to several others sends each of them every output, and one with several
+//! links coming in reads them all from the same queue, so chains, rings and any fan-in or
+//! fan-out are all just links. Everything runs on the [`asynchronous`](super::asynchronous)
+//! executor; a machine's input runs dry once its initial inputs are used up and every machine
+//! feeding it has halted.
+use super::asynchronous::{channel, AsyncIntcodeMachine, AsyncOutput, AsyncSender, Executor};
+use super::load_program;
+use failure::{Fallible, ResultExt};
+use permutohedron::Heap;
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::path::Path;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Literal(i32),
+    /// `$n`, filled in from the permutation being tried.
+    Phase(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Machine {
+    pub name: String,
+    pub program: Vec<i32>,
+    pub inputs: Vec<Value>,
+}
+
+/// A description file that doesn't make sense, with the line it went wrong on.
+#[derive(Debug)]
+pub struct ConfigError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        write!(w, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Topology {
+    pub machines: Vec<Machine>,
+    /// `(from, to)` indices into `machines`.
+    pub links: Vec<(usize, usize)>,
+    pub sinks: Vec<usize>,
+    pub permute: Vec<i32>,
+}
+
+/// What every sink output for one assignment of the phases.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Run {
+    pub phases: Vec<i32>,
+    pub outputs: Vec<(String, Vec<i32>)>,
+}
+
+impl Display for Run {
+    fn fmt(&self, w: &mut Formatter) -> FmtResult {
+        if !self.phases.is_empty() {
+            writeln!(w, "phases {:?}", self.phases)?;
+        }
+        for (name, outputs) in self.outputs.iter() {
+            writeln!(w, "{}: {:?}", name, outputs)?;
+        }
+        Ok(())
+    }
+}
+
+/// Sends every output to each linked machine, and keeps a copy if the machine is a sink.
+struct Fanout {
+    targets: Vec<AsyncSender>,
+    record: Option<Rc<RefCell<Vec<i32>>>>,
+}
+
+impl AsyncOutput for Fanout {
+    fn poll_output(&mut self, _cx: &mut Context, what: i32) -> Poll<Fallible<()>> {
+        for target in self.targets.iter() {
+            target.send(what);
+        }
+        if let Some(record) = &self.record {
+            record.borrow_mut().push(what);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn results(&self) -> Option<Vec<i32>> {
+        self.record.as_ref().map(|record| record.borrow().clone())
+    }
+}
+
+impl Topology {
+    /// Reads a description, getting each program from `load` by the name given for it.
+    pub fn parse(
+        text: &str,
+        mut load: impl FnMut(&str) -> Fallible<Vec<i32>>,
+    ) -> Fallible<Topology> {
+        let mut topology = Topology::default();
+        let mut phases = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let error = |message: String| ConfigError {
+                line: n + 1,
+                message,
+            };
+            let words: Vec<&str> = line.split('#').next().unwrap().split_whitespace().collect();
+            let find = |name: &str| {
+                topology
+                    .machines
+                    .iter()
+                    .position(|m| m.name == name)
+                    .ok_or_else(|| error(format!("no machine called {}", name)))
+            };
+            match words[..] {
+                [] => {}
+                ["machine", name, program, ref inputs @ ..] => {
+                    if find(name).is_ok() {
+                        return Err(error(format!("{} is already defined", name)).into());
+                    }
+                    let inputs = inputs
+                        .iter()
+                        .map(|word| match word.strip_prefix('$') {
+                            Some(phase) => phase.parse().map(Value::Phase),
+                            None => word.parse().map(Value::Literal),
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|e| error(format!("bad input for {}: {}", name, e)))?;
+                    phases.extend(inputs.iter().filter_map(|value| match value {
+                        Value::Phase(phase) => Some((n + 1, *phase)),
+                        Value::Literal(_) => None,
+                    }));
+                    let program = load(program).with_context(|_| error(program.to_string()))?;
+                    topology.machines.push(Machine {
+                        name: name.to_string(),
+                        program,
+                        inputs,
+                    });
+                }
+                ["link", from, to] => {
+                    let link = (find(from)?, find(to)?);
+                    topology.links.push(link);
+                }
+                ["sink", name] => {
+                    let sink = find(name)?;
+                    topology.sinks.push(sink);
+                }
+                ["permute", ref values @ ..] => {
+                    topology.permute = values
+                        .iter()
+                        .map(|word| word.parse())
+                        .collect::<Result<_, _>>()
+                        .map_err(|e| error(format!("bad value to permute: {}", e)))?;
+                }
+                _ => return Err(error(format!("can't make sense of {:?}", line.trim())).into()),
+            }
+        }
+        for (line, phase) in phases {
+            if phase >= topology.permute.len() {
+                let message = format!("${} needs at least {} values to permute", phase, phase + 1);
+                return Err(ConfigError { line, message }.into());
+            }
+        }
+        Ok(topology)
+    }
+
+    /// Reads a description file. Program names are relative to the file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Fallible<Topology> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).with_context(|_| path.display().to_string())?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        Topology::parse(&text, |program| load_program(dir.join(program)))
+    }
+
+    /// Runs every machine to completion with `$n` replaced by `phases[n]`.
+    pub fn run_with(&self, phases: &[i32]) -> Fallible<Run> {
+        let channels: Vec<_> = self.machines.iter().map(|_| channel()).collect();
+        let records: Vec<_> = self
+            .sinks
+            .iter()
+            .map(|_| Rc::new(RefCell::new(Vec::new())))
+            .collect();
+
+        let mut executor = Executor::default();
+        let mut senders = Vec::new();
+        for (i, (machine, (tx, _))) in self.machines.iter().zip(channels.iter()).enumerate() {
+            for value in machine.inputs.iter() {
+                tx.send(match value {
+                    Value::Literal(x) => *x,
+                    Value::Phase(phase) => phases[*phase],
+                });
+            }
+            let targets = self
+                .links
+                .iter()
+                .filter(|(from, _)| *from == i)
+                .map(|(_, to)| channels[*to].0.clone())
+                .collect();
+            let record = self.sinks.iter().position(|sink| *sink == i);
+            senders.push(Fanout {
+                targets,
+                record: record.map(|r| records[r].clone()),
+            });
+        }
+        // only the machines feeding a queue should keep it open
+        for ((machine, (_, rx)), output) in self.machines.iter().zip(channels).zip(senders) {
+            let mut cpu =
+                AsyncIntcodeMachine::new(&machine.program, Box::new(rx), Box::new(output));
+            executor.spawn(async move { cpu.run().await });
+        }
+
+        for (machine, result) in self.machines.iter().zip(executor.run()) {
+            result.with_context(|_| format!("machine {}", machine.name))?;
+        }
+        Ok(Run {
+            phases: phases.to_vec(),
+            outputs: self
+                .sinks
+                .iter()
+                .zip(records)
+                .map(|(sink, record)| (self.machines[*sink].name.clone(), record.take()))
+                .collect(),
+        })
+    }
+
+    /// Runs the network, or with `permute`, every ordering of its values, returning the one
+    /// whose sinks ended on the largest outputs.
+    pub fn run(&self) -> Fallible<Run> {
+        if self.permute.is_empty() {
+            return self.run_with(&[]);
+        }
+        let mut values = self.permute.clone();
+        let mut best: Option<Run> = None;
+        for phases in Heap::new(&mut values) {
+            let run = self.run_with(&phases)?;
+            let last = |run: &Run| -> Vec<Option<i32>> {
+                run.outputs
+                    .iter()
+                    .map(|(_, out)| out.last().cloned())
+                    .collect()
+            };
+            if best.as_ref().is_none_or(|best| last(&run) > last(best)) {
+                best = Some(run);
+            }
+        }
+        Ok(best.unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAIN: &[i32] = &[
+        3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+    ];
+
+    const FEEDBACK: &[i32] = &[
+        3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1, 28,
+        1005, 28, 6, 99, 0, 0, 5,
+    ];
+
+    fn amplifiers(program: &'static [i32], ring: bool) -> Topology {
+        let mut text = String::from("machine a amp $0 0\n");
+        for (prev, name) in "abcde".chars().zip("bcde".chars()) {
+            text += &format!("machine {} amp ${}\n", name, name as u8 - b'a');
+            text += &format!("link {} {}\n", prev, name);
+        }
+        if ring {
+            text += "link e a\n";
+        }
+        text += "sink e\n";
+        text += if ring {
+            "permute 5 6 7 8 9"
+        } else {
+            "permute 0 1 2 3 4"
+        };
+        Topology::parse(&text, |_| Ok(program.to_vec())).unwrap()
+    }
+
+    #[test]
+    fn test_chain() {
+        let topology = amplifiers(CHAIN, false);
+        let run = topology.run_with(&[4, 3, 2, 1, 0]).unwrap();
+        assert_eq!(run.outputs, vec![("e".to_string(), vec![43210])]);
+        assert_eq!(topology.run().unwrap().outputs[0].1, vec![43210]);
+    }
+
+    #[test]
+    fn test_ring() {
+        let run = amplifiers(FEEDBACK, true).run().unwrap();
+        assert_eq!(run.phases, vec![9, 8, 7, 6, 5]);
+        assert_eq!(run.outputs[0].1.last(), Some(&139629729));
+    }
+
+    #[test]
+    fn test_fan_out_and_in() {
+        // a doubles into b and c, which both add one into d, which echoes everything
+        let text = "
+            machine a double 1 2
+            machine b increment
+            machine c increment
+            machine d echo   # fed by both
+            link a b
+            link a c
+            link b d
+            link c d
+            sink a
+            sink d
+        ";
+        let program = |name: &str| {
+            Ok(match name {
+                "double" => vec![
+                    3, 17, 102, 2, 17, 17, 4, 17, 3, 17, 102, 2, 17, 17, 4, 17, 99, 0,
+                ],
+                "increment" => vec![
+                    3, 17, 1001, 17, 1, 17, 4, 17, 3, 17, 1001, 17, 1, 17, 4, 17, 99, 0,
+                ],
+                _ => vec![
+                    3, 17, 4, 17, 3, 17, 4, 17, 3, 17, 4, 17, 3, 17, 4, 17, 99, 0,
+                ],
+            })
+        };
+        let run = Topology::parse(text, program).unwrap().run().unwrap();
+        assert_eq!(run.outputs[0], ("a".to_string(), vec![2, 4]));
+        let mut merged = run.outputs[1].1.clone();
+        merged.sort();
+        assert_eq!(merged, vec![3, 3, 5, 5]);
+        assert_eq!(
+            run.to_string(),
+            format!("a: [2, 4]\nd: {:?}\n", run.outputs[1].1)
+        );
+    }
+
+    #[test]
+    fn test_config_errors() {
+        let load = |_: &str| Ok(vec![99]);
+        let error = |text| Topology::parse(text, load).unwrap_err().to_string();
+        assert_eq!(
+            error("machine a x\nlink a b"),
+            "line 2: no machine called b"
+        );
+        assert_eq!(
+            error("machine a x\nmachine a x"),
+            "line 2: a is already defined"
+        );
+        assert_eq!(
+            error("machine a x $1\npermute 1"),
+            "line 1: $1 needs at least 2 values to permute"
+        );
+        assert_eq!(
+            error("machine a x one"),
+            "line 1: bad input for a: invalid digit found in string"
+        );
+        assert_eq!(error("sink"), "line 1: can't make sense of \"sink\"");
+    }
+}
@@ -0,0 +1,187 @@
+//! Drawing a running machine on an ANSI terminal.
+//!
+//! A [`Recorder`] hook keeps track of what the machine has been up to, and [`render`] turns the
+//! machine plus that record into one frame: memory as a grid with the current instruction in
+//! reverse video, recent writes in a red that fades with age, the decoded instruction, and the
+//! I/O so far.
+use super::hooks::Hook;
+use super::{IntcodeMachine, Opcode};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::rc::Rc;
+
+/// Brightest to dimmest, from the 256 colour palette.
+const FADE: [u8; 5] = [196, 160, 124, 88, 52];
+
+#[derive(Debug, Default)]
+pub struct Activity {
+    /// Instructions fetched so far.
+    pub steps: usize,
+    /// The step each cell was last written on.
+    pub writes: HashMap<usize, usize>,
+    pub inputs: Vec<i32>,
+    pub outputs: Vec<i32>,
+}
+
+#[derive(Default)]
+pub struct Recorder {
+    activity: Rc<RefCell<Activity>>,
+}
+
+impl Recorder {
+    pub fn new() -> Recorder {
+        Recorder::default()
+    }
+
+    pub fn activity(&self) -> Rc<RefCell<Activity>> {
+        self.activity.clone()
+    }
+}
+
+impl Hook for Recorder {
+    fn on_fetch(&mut self, _ip: usize, _op: &Opcode) {
+        self.activity.borrow_mut().steps += 1;
+    }
+
+    fn on_write(&mut self, addr: usize, _old: i32, _new: i32) {
+        let mut activity = self.activity.borrow_mut();
+        let step = activity.steps;
+        activity.writes.insert(addr, step);
+    }
+
+    fn on_input(&mut self, value: i32) {
+        self.activity.borrow_mut().inputs.push(value);
+    }
+
+    fn on_output(&mut self, value: i32) {
+        self.activity.borrow_mut().outputs.push(value);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct View {
+    pub columns: usize,
+    /// Rows of memory to show, centred on the ip.
+    pub rows: usize,
+    pub hex: bool,
+    /// Writes older than this many steps are drawn normally again.
+    pub fade: usize,
+}
+
+impl Default for View {
+    fn default() -> View {
+        View {
+            columns: 16,
+            rows: 20,
+            hex: false,
+            fade: 50,
+        }
+    }
+}
+
+fn cell(value: i32, hex: bool) -> String {
+    match (hex, value < 0) {
+        (false, _) => format!("{:>7}", value),
+        (true, false) => format!("{:>7x}", value),
+        (true, true) => format!("{:>7}", format!("-{:x}", value.unsigned_abs())),
+    }
+}
+
+/// One frame, starting with the cursor homed. `pending` is the input not read yet.
+pub fn render(
+    machine: &IntcodeMachine,
+    activity: &Activity,
+    pending: &[i32],
+    view: &View,
+) -> String {
+    let memory = machine.memory();
+    let ip = machine.ip();
+    let decoded = Opcode::decode(memory, ip);
+    let current = ip..ip + decoded.as_ref().map_or(1, Opcode::len);
+
+    let mut frame = String::from("\x1b[H");
+    let next = match &decoded {
+        Ok(op) => op.to_string(),
+        Err(e) => format!("<{}>", e),
+    };
+    writeln!(
+        frame,
+        "step {:<8} ip {:<6} {}\x1b[K",
+        activity.steps, ip, next
+    )
+    .unwrap();
+
+    let columns = view.columns.max(1);
+    let total_rows = memory.len().div_ceil(columns);
+    let first = (ip / columns)
+        .saturating_sub(view.rows / 2)
+        .min(total_rows.saturating_sub(view.rows));
+    for row in first..(first + view.rows).min(total_rows) {
+        write!(frame, "{:>6} |", row * columns).unwrap();
+        let start = row * columns;
+        let cells = memory[start..].iter().take(columns);
+        for (addr, value) in (start..).zip(cells) {
+            let text = cell(*value, view.hex);
+            let age = activity
+                .writes
+                .get(&addr)
+                .map(|step| activity.steps - step)
+                .filter(|age| *age < view.fade);
+            match (current.contains(&addr), age) {
+                (true, _) => write!(frame, "\x1b[7m{}\x1b[0m", text),
+                (false, Some(age)) => {
+                    let colour = FADE[age * FADE.len() / view.fade.max(1)];
+                    write!(frame, "\x1b[38;5;{}m{}\x1b[0m", colour, text)
+                }
+                (false, None) => write!(frame, "{}", text),
+            }
+            .unwrap();
+        }
+        writeln!(frame, "\x1b[K").unwrap();
+    }
+
+    writeln!(frame, "in:  {:?} <- {:?}\x1b[K", activity.inputs, pending).unwrap();
+    writeln!(frame, "out: {:?}\x1b[K", activity.outputs).unwrap();
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame() {
+        let data = &[3, 9, 1001, 9, 1, 9, 4, 9, 99, 0];
+        let mut machine = IntcodeMachine::new(data, Box::new(vec![41]), Box::new(Vec::new()));
+        let recorder = Recorder::new();
+        let activity = recorder.activity();
+        machine.add_hook(Box::new(recorder));
+        for _ in 0..2 {
+            let op = machine.unpack_op().unwrap();
+            super::super::Instruction::execute(op, &mut machine).unwrap();
+        }
+        let view = View {
+            columns: 5,
+            ..View::default()
+        };
+        let frame = render(&machine, &activity.borrow(), &[], &view);
+        let lines: Vec<&str> = frame.lines().collect();
+        assert_eq!(
+            lines[0],
+            "\x1b[Hstep 2        ip 6      (9) -> output\x1b[K"
+        );
+        assert_eq!(
+            lines[2],
+            "     5 |      9\x1b[7m      4\x1b[0m\x1b[7m      9\x1b[0m     99\x1b[38;5;196m     42\x1b[0m\x1b[K"
+        );
+        assert_eq!(lines[3], "in:  [41] <- []\x1b[K");
+    }
+
+    #[test]
+    fn test_hex_cells() {
+        assert_eq!(cell(255, true), "     ff");
+        assert_eq!(cell(-255, true), "    -ff");
+        assert_eq!(cell(-255, false), "   -255");
+    }
+}